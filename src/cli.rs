@@ -0,0 +1,78 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::explorer::graph_order::GraphOrder;
+
+#[cfg(test)]
+mod tests;
+
+pub const USAGE: &str = "usage: git_explorer [<rev>] [--repo <path>] [--stop <branch>] [--order <committer-date|author-date|topo|first-parent>] [--first-parent]";
+
+#[derive(Debug)]
+pub struct CliError(String);
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        if self.0.is_empty() {
+            write!(f, "{}", USAGE)
+        } else {
+            write!(f, "{}\n{}", self.0, USAGE)
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Command line options.
+///
+/// `<rev>` is any revspec understood by `git rev-parse` (full or short SHA, `HEAD~5`,
+/// `v1.2^2`, `origin/main@{yesterday}`, ...). When it is missing the explorer starts at HEAD.
+///
+/// The repository is found from `--repo <path>`, or from `GIT_DIR`/`GIT_WORK_TREE` and the
+/// current directory like git does.
+#[derive(Debug, Default)]
+pub struct Cli {
+    pub repo: Option<String>,
+    pub revspec: Option<String>,
+    pub stop_condition: Option<String>,
+    pub order: GraphOrder,
+}
+
+impl Cli {
+    pub fn parse(args: Vec<String>) -> Result<Self, CliError> {
+        let mut cli = Self::default();
+        let mut args = args.into_iter().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--repo" => {
+                    match args.next() {
+                        Some(repo) => cli.repo = Some(repo),
+                        None => return Err(CliError(String::from("--repo needs a path"))),
+                    }
+                },
+                "--stop" => {
+                    match args.next() {
+                        Some(stop_condition) => cli.stop_condition = Some(stop_condition),
+                        None => return Err(CliError(String::from("--stop needs a branch name"))),
+                    }
+                },
+                "--order" => {
+                    match args.next().as_deref().and_then(GraphOrder::parse) {
+                        Some(order) => cli.order = order,
+                        None => return Err(CliError(String::from("--order needs one of committer-date, author-date, topo, first-parent"))),
+                    }
+                },
+                "--first-parent" => cli.order = GraphOrder::FirstParent,
+                "-h" | "--help" => return Err(CliError(String::new())),
+                _ if arg.starts_with("--") => return Err(CliError(format!("unknown option '{}'", arg))),
+                _ => {
+                    if cli.revspec.is_some() {
+                        return Err(CliError(format!("unexpected argument '{}'", arg)))
+                    }
+                    cli.revspec = Some(arg);
+                }
+            }
+        }
+        Ok(cli)
+    }
+}
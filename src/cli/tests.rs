@@ -0,0 +1,59 @@
+use crate::cli::Cli;
+use crate::explorer::graph_order::GraphOrder;
+
+fn parse(args: &[&str]) -> Result<Cli, String> {
+    let args = std::iter::once("git_explorer").chain(args.iter().copied()).map(String::from).collect();
+    // The message alone, without the usage line.
+    Cli::parse(args).map_err(|error| error.0)
+}
+
+#[test]
+fn no_arguments_start_at_head() {
+    let cli = parse(&[]).unwrap();
+    assert_eq!(cli.revspec, None);
+    assert_eq!(cli.repo, None);
+    assert_eq!(cli.stop_condition, None);
+    assert_eq!(cli.order, GraphOrder::CommitterDate);
+}
+
+#[test]
+fn positional_revspec_among_options() {
+    let cli = parse(&["--repo", "/tmp/repo", "HEAD~5", "--stop", "main", "--order", "topo"]).unwrap();
+    assert_eq!(cli.revspec.as_deref(), Some("HEAD~5"));
+    assert_eq!(cli.repo.as_deref(), Some("/tmp/repo"));
+    assert_eq!(cli.stop_condition.as_deref(), Some("main"));
+    assert_eq!(cli.order, GraphOrder::Topological);
+}
+
+#[test]
+fn first_parent_flag() {
+    assert_eq!(parse(&["--first-parent"]).unwrap().order, GraphOrder::FirstParent);
+}
+
+#[test]
+fn options_missing_their_value() {
+    assert_eq!(parse(&["--repo"]).unwrap_err(), "--repo needs a path");
+    assert_eq!(parse(&["--stop"]).unwrap_err(), "--stop needs a branch name");
+    assert!(parse(&["--order"]).unwrap_err().starts_with("--order needs one of"));
+}
+
+#[test]
+fn unknown_order() {
+    assert!(parse(&["--order", "random"]).unwrap_err().starts_with("--order needs one of"));
+}
+
+#[test]
+fn unknown_option() {
+    assert_eq!(parse(&["--bogus"]).unwrap_err(), "unknown option '--bogus'");
+}
+
+#[test]
+fn second_positional_argument() {
+    assert_eq!(parse(&["HEAD", "main"]).unwrap_err(), "unexpected argument 'main'");
+}
+
+#[test]
+fn help_is_the_usage_alone() {
+    assert_eq!(parse(&["--help"]).unwrap_err(), "");
+    assert_eq!(parse(&["-h"]).unwrap_err(), "");
+}
@@ -1,5 +1,11 @@
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Result as FmtResult};
-use git2::{Oid, Error, Branch, BranchType};
+use std::path::{Path, PathBuf};
+use std::convert::TryFrom;
+
+use git2::{Oid, Branch, BranchType, Repository};
+
+use crate::explorer::error::ExplorerError;
 
 #[derive(Clone)]
 enum BranchKind {
@@ -17,75 +23,144 @@ impl From<BranchType> for BranchKind {
 }
 
 
+/// Commits a branch has that another doesn't (ahead) and the other way around (behind).
+#[derive(Clone, Copy)]
+pub struct AheadBehind {
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+impl Display for AheadBehind {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "↑{} ↓{}", self.ahead, self.behind)
+    }
+}
+
+/// Ahead/behind counts already computed, by branch tip and the commit it's compared to. Walking
+/// the history between two tips is slow on big repositories, and the answer never changes for
+/// the same two commits.
+#[derive(Default)]
+pub struct AheadBehindCache(HashMap<(Oid, Oid), Option<AheadBehind>>);
+
+impl AheadBehindCache {
+    fn get(&mut self, repo: &Repository, local: Oid, other: Oid) -> Option<AheadBehind> {
+        *self.0.entry((local, other)).or_insert_with(|| {
+            repo.graph_ahead_behind(local, other).ok().map(|(ahead, behind)| AheadBehind { ahead, behind })
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct BranchData {
     oid: Oid,
     shorthand: String,
     kind: Option<BranchKind>,
+    // Shorthand and tip of the upstream branch, if the branch tracks one.
+    upstream: Option<(String, Oid)>,
+    ahead_behind_head: Option<AheadBehind>,
+    ahead_behind_upstream: Option<AheadBehind>,
+    // Worktree the branch is checked out in.
+    worktree: Option<PathBuf>,
 }
 
-// impl From<(Branch<'_>, BranchType)> for BranchData {
-impl From<Result<(Branch<'_>, BranchType), Error>> for BranchData {
-    // fn from(b: (Branch, BranchType)) -> Self {
-    fn from(b: Result<(Branch, BranchType), Error>) -> Self {
-        match b {
-            Ok((branch, branch_type)) => {
-                let reference = branch.get();
-                let shorthand = reference.shorthand().unwrap().to_string();
-                let oid = reference.target().unwrap();
-                let kind = BranchKind::from(branch_type);
-                Self {
-                    oid,
-                    shorthand,
-                    // kind: Some(kind.into()),
-                    kind: Some(kind),
-                }
-            },
-            Err(e) => {
-                Self {
-                    oid: Oid::zero(),
-                    shorthand: String::new(),
-                    kind: None,
-                }
-            }
-        }
+impl TryFrom<(Branch<'_>, BranchType)> for BranchData {
+    type Error = ExplorerError;
+
+    fn try_from(branch: (Branch, BranchType)) -> Result<Self, ExplorerError> {
+        Self::new(branch)
     }
 }
 
 impl BranchData {
-    // pub fn new(branch: Result<((Branch, BranchType), <E>)>) -> Self {
-    pub fn new(branch: Result<(Branch, BranchType), Error>) -> Self {
-        match branch {
-            Ok((branch, branch_type)) => {
-                let reference = branch.get();
-                let shorthand = reference.shorthand().unwrap().to_string();
-                let oid = reference.target().unwrap();
-                let kind = BranchKind::from(branch_type);
-                Self {
-                    oid,
-                    shorthand,
-                    // kind: Some(kind.into()),
-                    kind: Some(kind),
+    pub fn new((branch, branch_type): (Branch, BranchType)) -> Result<Self, ExplorerError> {
+        let reference = branch.get();
+        // Ref names are bytes, an odd one is shown with replacement characters rather than failing.
+        let shorthand = String::from_utf8_lossy(reference.shorthand_bytes()).to_string();
+        // Symbolic branches like `origin/HEAD` are resolved to the commit they end up at.
+        let oid = reference.peel_to_commit()?.id();
+        let kind = BranchKind::from(branch_type);
+        let upstream = match branch.upstream() {
+            Ok(upstream) => {
+                let upstream = upstream.get();
+                match (upstream.shorthand(), upstream.target()) {
+                    (Some(shorthand), Some(oid)) => Some((shorthand.to_string(), oid)),
+                    _ => None,
                 }
             },
-            Err(e) => {
-                Self {
-                    oid: Oid::zero(),
-                    shorthand: String::new(),
-                    kind: None,
-                }
-            }
+            Err(_) => None,
+        };
+        Ok(Self {
+            oid,
+            shorthand,
+            // kind: Some(kind.into()),
+            kind: Some(kind),
+            upstream,
+            ahead_behind_head: None,
+            ahead_behind_upstream: None,
+            worktree: None,
+        })
+    }
+
+    /// Counts the commits ahead/behind HEAD and the upstream branch.
+    pub fn with_ahead_behind(mut self, repo: &Repository, cache: &mut AheadBehindCache) -> Self {
+        let head_oid = repo.head().ok().and_then(|head| head.target());
+        self.ahead_behind_head = head_oid.and_then(|head_oid| cache.get(repo, self.oid, head_oid));
+        self.ahead_behind_upstream = match &self.upstream {
+            Some((_, upstream_oid)) => cache.get(repo, self.oid, *upstream_oid),
+            None => None,
+        };
+        self
+    }
+
+    /// Looks the branch up among the branches checked out in a worktree.
+    pub fn with_worktree(mut self, worktrees: &HashMap<String, PathBuf>) -> Self {
+        if self.is_type(BranchType::Local) {
+            self.worktree = worktrees.get(&self.shorthand).cloned();
         }
+        self
     }
 
     pub fn shorthand(&self) -> &String { &self.shorthand }
 
     pub fn oid(&self) -> Oid { self.oid }
+
+    pub fn worktree(&self) -> Option<&Path> { self.worktree.as_deref() }
+
+    pub fn is_type(&self, branch_type: BranchType) -> bool {
+        matches!(
+            (&self.kind, branch_type),
+            (Some(BranchKind::Local), BranchType::Local) | (Some(BranchKind::Remote), BranchType::Remote)
+        )
+    }
+
+    /// `↑1 ↓2 (origin/main ↑0 ↓3)`: ahead/behind HEAD, then the upstream branch.
+    pub fn ahead_behind_label(&self) -> String {
+        let mut label = match self.ahead_behind_head {
+            Some(ahead_behind) => format!("{}", ahead_behind),
+            None => String::new(),
+        };
+        if let (Some((upstream, _)), Some(ahead_behind)) = (&self.upstream, self.ahead_behind_upstream) {
+            label.push_str(&format!(" ({} {})", upstream, ahead_behind));
+        }
+        label.trim().to_string()
+    }
+
+    /// Ahead/behind label followed by the worktree the branch is checked out in.
+    pub fn status_label(&self) -> String {
+        match self.worktree() {
+            Some(worktree) => format!("{} [{}]", self.ahead_behind_label(), worktree.display()).trim().to_string(),
+            None => self.ahead_behind_label(),
+        }
+    }
 }
 
 impl Display for BranchData {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        write!(f, "{}, ", self.shorthand)
+        let status = self.status_label();
+        if status.is_empty() {
+            write!(f, "{}, ", self.shorthand)
+        } else {
+            write!(f, "{} {}, ", self.shorthand, status)
+        }
     }
 }
-
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use git2::{Repository, Oid, Error, Sort};
+use log::trace;
+
+/// Reverse of the parent links: for every commit reachable from a reference (or HEAD), the
+/// commits that have it as a parent. Git only stores parent pointers, so this is the only way
+/// to walk forward in time.
+pub struct ChildIndex {
+    children: HashMap<Oid, Vec<Oid>>,
+}
+
+impl ChildIndex {
+    pub fn new(repo: &Repository) -> Result<Self, Error> {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TIME)?;
+        revwalk.push_glob("*")?;
+        // HEAD may be detached on a commit no reference points to.
+        if let Ok(head) = repo.head() {
+            if let Some(oid) = head.target() {
+                revwalk.push(oid)?;
+            }
+        }
+
+        let mut children: HashMap<Oid, Vec<Oid>> = HashMap::new();
+        for oid in revwalk {
+            let oid = oid?;
+            let commit = repo.find_commit(oid)?;
+            for parent_id in commit.parent_ids() {
+                children.entry(parent_id).or_default().push(oid);
+            }
+        }
+        trace!("ChildIndex::new {} commits with children", children.len());
+
+        Ok(Self { children })
+    }
+
+    /// Children of `oid`, newest first.
+    pub fn children(&self, oid: Oid) -> &[Oid] {
+        match self.children.get(&oid) {
+            Some(children) => children,
+            None => &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,51 @@
+use git2::Oid;
+
+use crate::explorer::child_index::ChildIndex;
+use crate::explorer::test_repo::TestRepo;
+
+#[test]
+fn children_of_a_fork_newest_first() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let b = test_repo.commit("B", &[a], 200);
+    let c = test_repo.commit("C", &[a], 300);
+    let d = test_repo.commit("D", &[a], 400);
+    let m = test_repo.commit("M", &[b, c], 500);
+    test_repo.branch("b", m);
+    test_repo.branch("d", d);
+
+    let child_index = ChildIndex::new(&test_repo.repo).unwrap();
+    assert_eq!(child_index.children(a), [d, c, b]);
+    // A merge is a child of each of its parents.
+    assert_eq!(child_index.children(b), [m]);
+    assert_eq!(child_index.children(c), [m]);
+    assert!(child_index.children(m).is_empty());
+}
+
+#[test]
+fn unknown_and_unreachable_commits_have_no_children() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let b = test_repo.commit("B", &[a], 200);
+    // No reference points at C, so it isn't known as a child of B.
+    let _c = test_repo.commit("C", &[b], 300);
+    test_repo.branch("main", b);
+
+    let child_index = ChildIndex::new(&test_repo.repo).unwrap();
+    assert_eq!(child_index.children(a), [b]);
+    assert!(child_index.children(b).is_empty());
+    let unknown = Oid::from_str("0123456789012345678901234567890123456789").unwrap();
+    assert!(child_index.children(unknown).is_empty());
+}
+
+#[test]
+fn a_detached_head_counts_as_a_reference() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let b = test_repo.commit("B", &[a], 200);
+    test_repo.branch("main", a);
+    test_repo.repo.set_head_detached(b).unwrap();
+
+    let child_index = ChildIndex::new(&test_repo.repo).unwrap();
+    assert_eq!(child_index.children(a), [b]);
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use git2::{Repository, Oid, ObjectType};
+
+// The order decorations are listed in next to a commit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DecorationKind {
+    Head,
+    LocalBranch,
+    RemoteBranch,
+    LightweightTag,
+    AnnotatedTag,
+    Stash,
+}
+
+/// A reference pointing at a painted commit, shown next to it in the graph.
+#[derive(Clone, Debug)]
+pub struct Decoration {
+    pub kind: DecorationKind,
+    pub name: String,
+}
+
+impl Display for Decoration {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self.kind {
+            DecorationKind::LightweightTag | DecorationKind::AnnotatedTag => write!(f, "tag: {}", self.name),
+            _ => write!(f, "{}", self.name),
+        }
+    }
+}
+
+impl Decoration {
+    /// References pointing at each commit, built in a single pass over the reference database
+    /// (loose and packed refs) so decorating a painted commit is a lookup.
+    pub fn load(repo: &Repository) -> HashMap<Oid, Vec<Self>> {
+        let mut decorations: HashMap<Oid, Vec<Self>> = HashMap::new();
+        let odb = repo.odb().ok();
+
+        if let Ok(head) = repo.head() {
+            if let Some(oid) = head.target() {
+                decorations.entry(oid).or_default().push(Self { kind: DecorationKind::Head, name: String::from("HEAD") });
+            }
+        }
+
+        let references = match repo.references() {
+            Ok(references) => references,
+            Err(_) => return decorations,
+        };
+        for reference in references.flatten() {
+            let (name, shorthand) = match (reference.name(), reference.shorthand()) {
+                (Some(name), Some(shorthand)) => (name, shorthand.to_string()),
+                _ => continue,
+            };
+            let kind = if name.starts_with("refs/heads/") {
+                DecorationKind::LocalBranch
+            } else if name.starts_with("refs/remotes/") {
+                DecorationKind::RemoteBranch
+            } else if name.starts_with("refs/tags/") {
+                let annotated = match (&odb, reference.target()) {
+                    (Some(odb), Some(oid)) => matches!(odb.read_header(oid), Ok((_, ObjectType::Tag))),
+                    _ => false,
+                };
+                if annotated { DecorationKind::AnnotatedTag } else { DecorationKind::LightweightTag }
+            } else if name == "refs/stash" {
+                DecorationKind::Stash
+            } else {
+                continue
+            };
+            // Branches point straight to commits, tags may have to be peeled.
+            let oid = match kind {
+                DecorationKind::LocalBranch | DecorationKind::RemoteBranch | DecorationKind::Stash => reference.resolve().ok().and_then(|r| r.target()),
+                _ => reference.peel_to_commit().ok().map(|commit| commit.id()),
+            };
+            if let Some(oid) = oid {
+                decorations.entry(oid).or_default().push(Self { kind, name: shorthand });
+            }
+        }
+
+        for node_decorations in decorations.values_mut() {
+            node_decorations.sort_by(|a, b| (a.kind, &a.name).cmp(&(b.kind, &b.name)));
+        }
+        decorations
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// What the selected commit is diffed against in the detail panel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffBase {
+    /// The nth parent, zero based. A root commit is diffed against the empty tree.
+    Parent(usize),
+    /// Every parent at once, keeping only what differs from all of them, like `git show --cc`.
+    Combined,
+}
+
+impl Default for DiffBase {
+    fn default() -> Self {
+        DiffBase::Parent(0)
+    }
+}
+
+impl DiffBase {
+    /// Next base of the runtime toggle for a commit with `parent_count` parents: each parent in
+    /// turn, then the combined diff for merges.
+    pub fn next(self, parent_count: usize) -> Self {
+        match self {
+            DiffBase::Parent(parent) if parent + 1 < parent_count => DiffBase::Parent(parent + 1),
+            DiffBase::Parent(_) if parent_count > 1 => DiffBase::Combined,
+            _ => DiffBase::Parent(0),
+        }
+    }
+}
+
+impl Display for DiffBase {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            DiffBase::Parent(parent) => write!(f, "parent {}", parent + 1),
+            DiffBase::Combined => write!(f, "combined"),
+        }
+    }
+}
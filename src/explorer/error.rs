@@ -0,0 +1,43 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use git2::Oid;
+
+use crate::utils::short_id;
+
+/// Everything that can go wrong while exploring a repository. The UI shows these in the status
+/// bar, only failing to open the repository or to resolve the starting revision is fatal.
+#[derive(Debug)]
+pub enum ExplorerError {
+    Open { path: String, source: git2::Error },
+    BadRevision { revspec: String, source: git2::Error },
+    CommitNotFound { oid: Oid, source: git2::Error },
+    Git(git2::Error),
+}
+
+impl Display for ExplorerError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            ExplorerError::Open { path, source } => write!(f, "not a git repository '{}': {}", path, source.message()),
+            ExplorerError::BadRevision { revspec, source } => write!(f, "bad revision '{}': {}", revspec, source.message()),
+            ExplorerError::CommitNotFound { oid, source } => write!(f, "commit {} not found: {}", short_id(*oid), source.message()),
+            ExplorerError::Git(source) => write!(f, "{}", source.message()),
+        }
+    }
+}
+
+impl std::error::Error for ExplorerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExplorerError::Open { source, .. }
+            | ExplorerError::BadRevision { source, .. }
+            | ExplorerError::CommitNotFound { source, .. }
+            | ExplorerError::Git(source) => Some(source),
+        }
+    }
+}
+
+impl From<git2::Error> for ExplorerError {
+    fn from(error: git2::Error) -> Self {
+        ExplorerError::Git(error)
+    }
+}
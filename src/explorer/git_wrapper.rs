@@ -1,6 +1,10 @@
-use git2::{Reference, Error, Repository, Branches, BranchType};
-use crate::explorer::branch_data::BranchData;
-use std::process;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use git2::{Reference, Error, Repository, Branches, BranchType, Commit, Oid};
+use crate::explorer::branch_data::{AheadBehindCache, BranchData};
+use crate::explorer::head_state::HeadState;
+use crate::explorer::error::ExplorerError;
 use log::error;
 
 pub struct GitWrapper {
@@ -9,56 +13,108 @@ pub struct GitWrapper {
 }
 
 impl GitWrapper {
-    pub fn new(path: Option<String>) -> Self {
-        let repo = match path.clone() {
-            Some(p) => {
-                match Repository::open(&p) {
-                    Ok(repo) => repo,
-                    Err(e) => { error!("failed to open '{}': {}", p, e); process::exit(0x0100); },
-                }
-            },
-            None => {
-                match Repository::open(".") {
-                    Ok(repo) => repo,
-                    Err(e) => { error!("failed to open current directory: {}", e); process::exit(0x0100); },
-                }
-            }
-        };
-        Self {
+    pub fn new(path: Option<String>) -> Result<Self, ExplorerError> {
+        let repo = open_repository(path.as_deref()).map_err(|source| {
+            error!("failed to open '{}': {}", path.as_deref().unwrap_or("."), source);
+            ExplorerError::Open { path: path.clone().unwrap_or_else(|| String::from(".")), source }
+        })?;
+        Ok(Self {
             path,
             repo,
-        }
+        })
     }
 
     pub fn head(&self) -> Result<Reference, Error> {
         self.repo.head()
     }
 
+    pub fn head_state(&self) -> HeadState {
+        HeadState::new(&self.repo)
+    }
+
     pub fn branches(&self, branch_type: Option<BranchType>) -> Result<Branches, Error> {
         self.repo.branches(branch_type)
     }
 
-    pub fn branches_data(&self, stop_condition: Option<BranchData>) -> Vec<Option<BranchData>> {
+    pub fn checked_out_branches(&self) -> HashMap<String, PathBuf> {
+        checked_out_branches(&self.repo)
+    }
+
+    pub fn branches_data(&self, stop_condition: Option<BranchData>) -> Result<Vec<Option<BranchData>>, ExplorerError> {
+        let worktrees = self.checked_out_branches();
+        let mut cache = AheadBehindCache::default();
+        let stop_condition = stop_condition.map(|branch_data| branch_data.with_ahead_behind(&self.repo, &mut cache).with_worktree(&worktrees));
         let mut stop_conditions: Vec<Option<BranchData>> = vec![stop_condition];
-        match self.repo.head() {
-            Ok(head) => {
-                for branch in self.repo.branches(Some(BranchType::Local)).unwrap() {
+        match self.head_state().branch_name() {
+            Some(head) => {
+                for branch in self.repo.branches(Some(BranchType::Local))? {
                     // let branch_data = BranchData::new(branch);
-                    let branch_data = BranchData::from(branch);
+                    let branch_data = BranchData::new(branch?)?.with_ahead_behind(&self.repo, &mut cache).with_worktree(&worktrees);
                     let b_string = branch_data.shorthand();
-                    let head = head.shorthand().unwrap().to_string();
-                    if head.contains(b_string) || b_string.contains(&head) {
+                    if head.contains(b_string) || b_string.contains(head) {
                         stop_conditions.push(Some(branch_data));
                     }
                 }
             },
-            Err(_) => {
-                for branch in self.repo.branches(Some(BranchType::Local)).unwrap() {
-                    let branch_data = BranchData::new(branch);
+            None => {
+                for branch in self.repo.branches(Some(BranchType::Local))? {
+                    let branch_data = BranchData::new(branch?)?.with_ahead_behind(&self.repo, &mut cache).with_worktree(&worktrees);
                     stop_conditions.push(Some(branch_data));
                 }
             }
         };
-        stop_conditions
+        Ok(stop_conditions)
+    }
+}
+
+/// Looks up a commit, telling which one is missing when it fails.
+pub fn find_commit(repo: &Repository, oid: Oid) -> Result<Commit<'_>, ExplorerError> {
+    repo.find_commit(oid).map_err(|source| ExplorerError::CommitNotFound { oid, source })
+}
+
+/// Opens the repository at or above `path`, or the one the environment points to (`GIT_DIR`,
+/// `GIT_WORK_TREE`, ...) searching up from the current directory. Bare repositories and linked
+/// worktrees open like any other.
+pub fn open_repository(path: Option<&str>) -> Result<Repository, Error> {
+    match path {
+        Some(path) => Repository::discover(path),
+        None => Repository::open_from_env(),
+    }
+}
+
+/// Worktree every local branch is checked out in, the main one included unless the repository
+/// is bare.
+pub fn checked_out_branches(repo: &Repository) -> HashMap<String, PathBuf> {
+    let mut branches = HashMap::new();
+    let mut add = |worktree_repo: &Repository| {
+        if let (Some(workdir), Ok(head)) = (worktree_repo.workdir(), worktree_repo.head()) {
+            if let (true, Some(name)) = (head.is_branch(), head.shorthand()) {
+                branches.insert(name.to_string(), workdir.to_path_buf());
+            }
+        }
+    };
+
+    // The git directory of a linked worktree is `<common dir>/worktrees/<name>`.
+    let common_dir = match repo.is_worktree() {
+        true => repo.path().parent().and_then(|worktrees| worktrees.parent()),
+        false => Some(repo.path()),
+    };
+    if let Some(main) = common_dir.and_then(|common_dir| Repository::open(common_dir).ok()) {
+        add(&main);
     }
+    if let Ok(names) = repo.worktrees() {
+        for name in names.iter().flatten() {
+            let worktree = match repo.find_worktree(name) {
+                Ok(worktree) => worktree,
+                Err(_) => continue,
+            };
+            if let Ok(worktree_repo) = Repository::open_from_worktree(&worktree) {
+                add(&worktree_repo);
+            }
+        }
+    }
+    branches
 }
+
+#[cfg(test)]
+mod tests;
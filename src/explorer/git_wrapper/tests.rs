@@ -0,0 +1,48 @@
+use std::fs;
+
+use git2::{Repository, WorktreeAddOptions};
+
+use crate::explorer::git_wrapper::{open_repository, checked_out_branches};
+use crate::explorer::test_repo::TestRepo;
+
+#[test]
+fn opens_the_repository_from_a_subdirectory() {
+    let test_repo = TestRepo::new();
+    let subdirectory = test_repo.path.join("src/deeply/nested");
+    fs::create_dir_all(&subdirectory).unwrap();
+
+    let repo = open_repository(subdirectory.to_str()).unwrap();
+    assert_eq!(repo.path(), test_repo.repo.path());
+}
+
+#[test]
+fn opens_bare_repositories() {
+    let test_repo = TestRepo::new();
+    let bare_path = test_repo.path.join("bare.git");
+    Repository::init_bare(&bare_path).unwrap();
+
+    let repo = open_repository(bare_path.to_str()).unwrap();
+    assert!(repo.is_bare());
+    assert!(checked_out_branches(&repo).is_empty());
+}
+
+#[test]
+fn branches_checked_out_in_linked_worktrees() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    test_repo.branch("main", a);
+    test_repo.branch("feature", a);
+    test_repo.repo.set_head("refs/heads/main").unwrap();
+    let feature = test_repo.repo.find_reference("refs/heads/feature").unwrap();
+    let worktree_path = test_repo.path.join("feature-worktree");
+    test_repo.repo.worktree("feature-worktree", &worktree_path, Some(WorktreeAddOptions::new().reference(Some(&feature)))).unwrap();
+
+    // Whichever worktree the repository is opened from, every checkout is listed.
+    let worktree_repo = open_repository(worktree_path.to_str()).unwrap();
+    for repo in [&test_repo.repo, &worktree_repo] {
+        let branches = checked_out_branches(repo);
+        assert_eq!(branches.len(), 2);
+        assert_eq!(fs::canonicalize(&branches["main"]).unwrap(), fs::canonicalize(&test_repo.path).unwrap());
+        assert_eq!(fs::canonicalize(&branches["feature"]).unwrap(), fs::canonicalize(&worktree_path).unwrap());
+    }
+}
@@ -1,30 +1,47 @@
 use git2::Oid;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use crate::utils::short_id;
+use crate::explorer::decoration::Decoration;
 
 #[derive(Clone, Debug)]
 pub struct GraphNode {
     pub grapheme: String,
     pub oid: Oid,
-    pub branch_shorthand: Option<String>,
+    // Branches, tags, HEAD... pointing to this commit.
+    pub decorations: Vec<Decoration>,
     pub summary: String,
-    // TODO: add commit summary
+    // Rows expanding a merge shows, only counted in the first parent order.
+    pub merged_count: Option<usize>,
+    // The side branch of the merge is shown in the first parent order.
+    pub expanded: bool,
+    // Merge base with the stop branch, painting stops here.
+    pub merge_base: bool,
 }
 
 impl GraphNode {
     pub fn id(&self) -> Oid {
         self.oid
     }
+
+    /// `(+3)` for a collapsed merge bringing in 3 commits, `(-3)` once expanded.
+    pub fn merged_label(&self) -> String {
+        match self.merged_count {
+            Some(count) if self.expanded => format!("(-{}) ", count),
+            Some(count) => format!("(+{}) ", count),
+            None => String::new(),
+        }
+    }
 }
  
 impl Display for GraphNode {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        let (grapheme, oid, branch_shorthand, summary) = (&self.grapheme, &self.oid, &self.branch_shorthand, &self.summary);
-        let branch_shorthand = match branch_shorthand {
-            Some(branch_shorthand) => { format!("[{}]", branch_shorthand) },
-            None => { String::new() },
+        let (grapheme, oid, summary) = (&self.grapheme, &self.oid, &self.summary);
+        let decorations = if self.decorations.is_empty() {
+            String::new()
+        } else {
+            format!("[{}]", self.decorations.iter().map(|d| d.to_string()).collect::<Vec<String>>().join(", "))
         };
-        write!(f, "{} ({}) {} {}", grapheme, short_id(*oid), branch_shorthand, summary)
+        write!(f, "{} ({}) {} {}{}", grapheme, short_id(*oid), decorations, self.merged_label(), summary)
     }
 }
 
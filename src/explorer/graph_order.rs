@@ -0,0 +1,51 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Order in which the kernel picks the next commit to paint among the lanes. Whatever the
+/// order, a commit is never painted before all of its children, even when clock skew makes a
+/// child look older than its parent.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum GraphOrder {
+    /// Newest committer date first, like `git log --date-order`.
+    #[default]
+    CommitterDate,
+    /// Newest author date first, like `git log --author-date-order`.
+    AuthorDate,
+    /// Keeps painting the same line of history as long as possible, like `git log --topo-order`.
+    Topological,
+    /// Committer date order following only the first parent of merges, like `git log --first-parent`.
+    FirstParent,
+}
+
+impl GraphOrder {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "committer-date" | "date" => Some(GraphOrder::CommitterDate),
+            "author-date" => Some(GraphOrder::AuthorDate),
+            "topo" | "topological" => Some(GraphOrder::Topological),
+            "first-parent" => Some(GraphOrder::FirstParent),
+            _ => None,
+        }
+    }
+
+    /// Next order of the runtime toggle.
+    pub fn next(self) -> Self {
+        match self {
+            GraphOrder::CommitterDate => GraphOrder::AuthorDate,
+            GraphOrder::AuthorDate => GraphOrder::Topological,
+            GraphOrder::Topological => GraphOrder::FirstParent,
+            GraphOrder::FirstParent => GraphOrder::CommitterDate,
+        }
+    }
+}
+
+impl Display for GraphOrder {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let name = match self {
+            GraphOrder::CommitterDate => "committer date",
+            GraphOrder::AuthorDate => "author date",
+            GraphOrder::Topological => "topological",
+            GraphOrder::FirstParent => "first parent",
+        };
+        write!(f, "{}", name)
+    }
+}
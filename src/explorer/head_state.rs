@@ -0,0 +1,65 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use git2::{Repository, Oid};
+
+use crate::utils::short_id;
+
+/// Where HEAD points. A freshly initialized repository, or one where the current branch has no
+/// commit yet, has an unborn HEAD and nothing to paint.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HeadState {
+    Branch { name: String, oid: Oid },
+    Detached(Oid),
+    Unborn(String),
+}
+
+impl HeadState {
+    pub fn new(repo: &Repository) -> Self {
+        let unborn_name = || {
+            let name = repo
+                .find_reference("HEAD")
+                .ok()
+                .and_then(|head| head.symbolic_target().map(String::from))
+                .unwrap_or_default();
+            name.trim_start_matches("refs/heads/").to_string()
+        };
+
+        match repo.head() {
+            Ok(head) => match head.peel_to_commit() {
+                Ok(commit) if repo.head_detached().unwrap_or(false) => HeadState::Detached(commit.id()),
+                Ok(commit) => HeadState::Branch {
+                    name: head.shorthand().unwrap_or("HEAD").to_string(),
+                    oid: commit.id(),
+                },
+                Err(_) => HeadState::Unborn(unborn_name()),
+            },
+            Err(_) => HeadState::Unborn(unborn_name()),
+        }
+    }
+
+    /// Commit HEAD points to, None when the branch is unborn.
+    pub fn oid(&self) -> Option<Oid> {
+        match self {
+            HeadState::Branch { oid, .. } | HeadState::Detached(oid) => Some(*oid),
+            HeadState::Unborn(_) => None,
+        }
+    }
+
+    /// Name of the current branch, born or not.
+    pub fn branch_name(&self) -> Option<&str> {
+        match self {
+            HeadState::Branch { name, .. } | HeadState::Unborn(name) => Some(name),
+            HeadState::Detached(_) => None,
+        }
+    }
+}
+
+impl Display for HeadState {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            HeadState::Branch { name, .. } => write!(f, "{}", name),
+            HeadState::Detached(oid) => write!(f, "detached at {}", short_id(*oid)),
+            HeadState::Unborn(name) => write!(f, "unborn branch {}", name),
+        }
+    }
+}
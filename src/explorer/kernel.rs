@@ -1,6 +1,14 @@
-use git2::{Repository, Commit, Oid, Time, BranchType};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use git2::{Repository, Commit, Oid, Time};
 use crate::explorer::graph_node::GraphNode;
 use crate::explorer::branch_data::BranchData;
+use crate::explorer::decoration::Decoration;
+use crate::explorer::graph_order::GraphOrder;
+use crate::explorer::error::ExplorerError;
+use crate::explorer::git_wrapper::find_commit;
+use crate::explorer::head_state::HeadState;
+use crate::explorer::lanes::Lanes;
 use crate::explorer::ParsedDiff;
 use log::trace;
 
@@ -9,35 +17,112 @@ use tui::{
     text::Span,
 };
 
-const LIMIT_STACK: usize = 500;
+/// Rows painted when the graph is (re)started. More rows are painted on demand by `ensure_nodes`.
+const INITIAL_ROWS: usize = 100;
+/// Rows painted every time the cursor gets close to the end of the painted graph.
+pub const EXPAND_ROWS: usize = 100;
+/// How much older than the commit about to be painted discovery goes. A child committed more
+/// than this before its parent, on history not discovered yet, is found after the parent is
+/// painted: the graph is then painted again, discovering the whole history first.
+const CLOCK_SKEW_SECONDS: i64 = 60 * 60;
 
 
 pub struct Kernel {
-    root_oid: Option<Oid>,
+    // Commits the graph is painted from, newest first. Empty means HEAD.
+    roots: Vec<Oid>,
     pub nodes: Vec<GraphNode>,
     is_updated: bool,
     stop_condition_i: usize,
     stop_conditions: Vec<Option<BranchData>>,
     nodes_len: usize,
-    abort: bool,
-    limit_stack: Option<usize>,
     stop_at_node_i: Option<usize>,
+    // Commits waiting to be painted, one per lane. Painting resumes from here.
+    lanes: Lanes,
+    // References pointing at each commit, loaded once per run.
+    decorations: HashMap<Oid, Vec<Decoration>>,
+    order: GraphOrder,
+    // Children not painted yet of every commit discovered so far. A commit is only painted once
+    // it has none left.
+    pending_children: HashMap<Oid, usize>,
+    // Commits whose parents are counted in `pending_children`, and those reached but not counted
+    // yet, newest first. Discovery only runs as far ahead of painting as it needs to.
+    discovered: HashSet<Oid>,
+    undiscovered: BinaryHeap<(i64, Oid)>,
+    // Commits painted since the graph was (re)started, a lane is never opened for them again.
+    painted: HashSet<Oid>,
+    // A commit was discovered after one of its parents got painted. Once that happened, every
+    // run discovers the whole history before painting.
+    skew_found: bool,
+    discover_all: bool,
+    // Lane of the last painted commit, the topological order keeps going down that lane.
+    last_lane: Option<usize>,
+    // Merges whose side branches are shown in the first parent order.
+    expanded: HashSet<Oid>,
+    // Commits each merge brings in when expanded, counted the first time it's painted. History
+    // doesn't change, they're kept across runs.
+    merged_counts: HashMap<Oid, usize>,
+    // With a stop condition, the commits that are painted and the merge bases painting stops at.
+    visible: Option<HashSet<Oid>>,
+    merge_bases: HashSet<Oid>,
 }
 
 impl Kernel {
-    pub fn new(root_oid: Option<Oid>, stop_conditions: Vec<Option<BranchData>>) -> Self {
+    pub fn new(root_oid: Option<Oid>, stop_conditions: Vec<Option<BranchData>>, order: GraphOrder) -> Self {
         Self {
-            abort: false,
-            limit_stack: Some(LIMIT_STACK),
             stop_condition_i: 0,
-            root_oid,
+            roots: root_oid.into_iter().collect(),
             stop_conditions,
             nodes: vec![],
             is_updated: false,
             nodes_len: 0,
             stop_at_node_i: None,
+            lanes: Lanes::new(&[]),
+            decorations: HashMap::new(),
+            order,
+            pending_children: HashMap::new(),
+            discovered: HashSet::new(),
+            undiscovered: BinaryHeap::new(),
+            painted: HashSet::new(),
+            skew_found: false,
+            discover_all: false,
+            last_lane: None,
+            expanded: HashSet::new(),
+            merged_counts: HashMap::new(),
+            visible: None,
+            merge_bases: HashSet::new(),
+        }
+    }
+
+    pub fn order(&self) -> GraphOrder {
+        self.order
+    }
+
+    /// Paints the graph from `oid` instead of HEAD. Takes effect on the next run.
+    pub fn set_root(&mut self, oid: Oid) {
+        self.roots = vec![oid];
+    }
+
+    /// Replaces the branches `update_graph` cycles through, selecting the first one. Takes
+    /// effect on the next run.
+    pub fn set_stop_conditions(&mut self, stop_conditions: Vec<Option<BranchData>>) {
+        self.stop_conditions = stop_conditions;
+        self.stop_condition_i = 0;
+    }
 
+    pub fn set_order(&mut self, order: GraphOrder, repo: &Repository) -> Result<(), ExplorerError> {
+        self.order = order;
+        self.run(repo)
+    }
+
+    /// Shows or hides the side branch brought in by the merge `oid` in the first parent order,
+    /// keeping at least as many rows painted as before.
+    pub fn toggle_expanded(&mut self, oid: Oid, repo: &Repository) -> Result<(), ExplorerError> {
+        if !self.expanded.remove(&oid) {
+            self.expanded.insert(oid);
         }
+        let nodes_len = self.nodes_len;
+        self.run(repo)?;
+        self.ensure_nodes(nodes_len, repo)
     }
     pub fn stop_branch(&mut self, stop_at_node_i: Option<usize>) {
         self.stop_at_node_i = stop_at_node_i;
@@ -52,7 +137,7 @@ impl Kernel {
             .map(|(i, sc)| {
                 // let s = sc.unwrap_or_else(|| { (Oid::zero(), String::from(format!("{}/{} None, ", self.stop_condition_i, self.stop_conditions.len())) )}).1;
                 let s = match sc {
-                    Some(sc) => sc.shorthand().clone(),
+                    Some(sc) => format!("{} {}", sc.shorthand(), sc.status_label()).trim().to_string(),
                     None => String::from(format!("{}/{} None", self.stop_condition_i + 1, self.stop_conditions.len())),
                 };
 
@@ -81,14 +166,13 @@ impl Kernel {
     }
 
     pub fn get_selected_branch_oid(&self) -> Option<Oid> {
-        let a = self.stop_conditions.get(self.stop_condition_i).unwrap().to_owned();
-        match a {
-            Some(branch_data) => Some(branch_data.oid()),
-            None => None
+        match self.stop_conditions.get(self.stop_condition_i) {
+            Some(Some(branch_data)) => Some(branch_data.oid()),
+            _ => None
         }
     }
 
-    pub fn update_graph(&mut self, i: isize, repo: &Repository) {
+    pub fn update_graph(&mut self, i: isize, repo: &Repository) -> Result<(), ExplorerError> {
         if i > 0 {
             if self.stop_condition_i < (self.stop_conditions.len() - 1) {
                 self.stop_condition_i = self.stop_condition_i + 1
@@ -105,37 +189,62 @@ impl Kernel {
         self.run(repo)
     }
 
-    // TODO: merge fn diff_commit and diff_commit_by_id using Generic types.
-    // pub fn diff_commit(&self, commit_1: Commit, commit_2: &Option<&GraphNode>) -> String {
-    pub fn diff_commit(&self, commit_1: Commit, i_2: usize, repo: &Repository) -> ParsedDiff {
-        // let parsed_diff = 
-        let commit_2 = self.get_node_id(i_2);
-        let parsed_diff = ParsedDiff::new(commit_1, commit_2, &repo);
-        // detail
-        parsed_diff
-    }
-
-    pub fn diff_commit_by_id(&self, commit_1: Commit, commit_2: Option<Oid>, repo: &Repository) -> ParsedDiff {
+    pub fn diff_commit_by_id(&self, commit_1: Commit, commit_2: Option<Oid>, repo: &Repository) -> Result<ParsedDiff, ExplorerError> {
         // let parsed_diff = 
         // let commit_2 = self.get_node_id(i_2);
         ParsedDiff::new(commit_1, commit_2, &repo)
     }
 
-    pub fn run(&mut self, repo: &Repository) {
+    pub fn run(&mut self, repo: &Repository) -> Result<(), ExplorerError> {
         trace!("fn run");
-        let nodes = match self.root_oid {
-            _ => {
-                let branches_tmp = repo.branches(Some(BranchType::Local)).unwrap(); // TODO use BranchData
-                let mut branches: Vec<BranchData> = vec![];
-                for b in branches_tmp {
-                    branches.push(BranchData::from(b));
-                }
-                // let branches = branches.map(|b| BranchData::new(b)).collect();
-                self.paint_commit_track(repo.head().unwrap().peel_to_commit().unwrap(), branches, repo)
-            }
+        // An unborn HEAD leaves nothing to paint.
+        let root_commits = if self.roots.is_empty() {
+            HeadState::new(repo).oid().into_iter().map(|oid| find_commit(repo, oid)).collect::<Result<Vec<Commit>, _>>()?
+        } else {
+            self.roots.iter().map(|oid| find_commit(repo, *oid)).collect::<Result<Vec<Commit>, _>>()?
         };
+        self.decorations = Decoration::load(repo);
+        let nodes = self.paint_commit_track(root_commits, repo)?;
         self.nodes_len = nodes.len();
         self.nodes = nodes;
+        Ok(())
+    }
+
+    /// Paints the graph from `oid` too, so history newer than the current roots shows up above them.
+    pub fn add_root(&mut self, oid: Oid, repo: &Repository) -> Result<(), ExplorerError> {
+        if self.roots.is_empty() {
+            self.roots.extend(HeadState::new(repo).oid());
+        }
+        if !self.roots.contains(&oid) {
+            self.roots.insert(0, oid);
+        }
+        self.run(repo)
+    }
+
+    pub fn find_node(&self, oid: Oid) -> Option<usize> {
+        self.nodes.iter().position(|node| node.id() == oid)
+    }
+
+    /// True when every commit reachable from the root (or up to the stop condition) is painted.
+    pub fn is_exhausted(&self) -> bool {
+        self.lanes.is_empty()
+    }
+
+    /// Paints more history until there are at least `len` nodes or nothing is left to paint.
+    pub fn ensure_nodes(&mut self, len: usize, repo: &Repository) -> Result<(), ExplorerError> {
+        if self.nodes_len < len && !self.is_exhausted() {
+            trace!("fn ensure_nodes {} < {}", self.nodes_len, len);
+            let mut nodes = self.paint_rows((len - self.nodes_len).max(EXPAND_ROWS), repo)?;
+            self.nodes.append(&mut nodes);
+            self.nodes_len = self.nodes.len();
+            if self.skew_found && !self.discover_all {
+                trace!("fn ensure_nodes clock skew found, painting again");
+                self.discover_all = true;
+                self.run(repo)?;
+                return self.ensure_nodes(len, repo)
+            }
+        }
+        Ok(())
     }
 
     fn find_max_index(&self, times: Vec<Time>) -> usize {
@@ -152,144 +261,245 @@ impl Kernel {
         max_index
     }
 
-    fn paint(&self, l: usize, max_index: usize, new_branch: bool) -> String {
-        let branches_string = if new_branch {
-            format!("{}├●{}",
-               String::from("│ ").repeat(max_index),
-               String::from("│ ").repeat(l - (max_index + 1)),
-            )
-        } else {
-            format!("{}├●",
-               String::from("│ ").repeat(l - 1),
-            )
+    /// Parents the graph follows from `commit`.
+    fn parent_ids(&self, commit: &Commit) -> Vec<Oid> {
+        if self.merge_bases.contains(&commit.id()) {
+            return vec![]
+        }
+        let parent_ids: Vec<Oid> = match self.order {
+            GraphOrder::FirstParent if !self.expanded.contains(&commit.id()) => commit.parent_id(0).into_iter().collect(),
+            _ => commit.parent_ids().collect(),
         };
-        format!("{}", branches_string)
-    }
-
-    fn abort(&mut self, commits_len: usize) -> bool {
-        match self.limit_stack {
-            Some(limit_stack) => {
-                let result = self.abort || commits_len == 0 || limit_stack == 0;
-                self.limit_stack = Some(limit_stack - 1);
-                result
-            },
-            None => self.abort || commits_len == 0
+        match &self.visible {
+            Some(visible) => parent_ids
+                .into_iter()
+                .filter(|parent| visible.contains(parent) || self.merge_bases.contains(parent))
+                .collect(),
+            None => parent_ids,
         }
     }
 
-    // fn maybe_set_abort(&mut self, current_drawn_commit: Commit, target_branch_data: &Option<BranchData>) {
-    fn maybe_set_abort(&mut self, current_drawn_commit: &Commit) {
-        if self.abort {return}
-        let stop_condition = self.stop_conditions.get(self.stop_condition_i);
-        self.abort = match stop_condition {
-            Some(stop_condition) => {
-                match stop_condition.to_owned() {
-                    Some(branch_data) => branch_data.oid() == current_drawn_commit.id(),
-                    None => false
+    /// Number of rows expanding a merge shows: the first parent line of each side branch, down
+    /// to where it joins the mainline or an earlier side branch. Both lines are walked newest
+    /// first, the mainline only as far back as the side branch goes. None for commits that
+    /// aren't merges.
+    fn merged_count(&mut self, commit: &Commit, repo: &Repository) -> Result<Option<usize>, ExplorerError> {
+        if commit.parent_count() < 2 {
+            return Ok(None)
+        }
+        if let Some(count) = self.merged_counts.get(&commit.id()) {
+            return Ok(Some(*count))
+        }
+        let mut mainline = Some(find_commit(repo, commit.parent_id(0)?)?);
+        let mut seen: HashSet<Oid> = HashSet::new();
+        let mut count = 0;
+        for side_id in commit.parent_ids().skip(1) {
+            let mut side = Some(find_commit(repo, side_id)?);
+            while let Some(side_commit) = side {
+                while let Some(main_commit) = mainline.take() {
+                    if main_commit.time() < side_commit.time() {
+                        mainline = Some(main_commit);
+                        break
+                    }
+                    seen.insert(main_commit.id());
+                    mainline = main_commit.parents().next();
+                }
+                if !seen.insert(side_commit.id()) {
+                    break
                 }
+                count += 1;
+                side = side_commit.parents().next();
             }
-            _ => false
-        };
+        }
+        self.merged_counts.insert(commit.id(), count);
+        Ok(Some(count))
     }
 
-    fn short_hand_current_commit(&self, branches: &Vec<BranchData>, repo: &Repository, commit_max: &Commit) -> Option<String> {
-        let mut i_branch = 0;
-        let mut shorthand: Option<String> = None;
-        for branch in branches.iter() {
-            let commit_branch = repo.find_commit(branch.oid()).unwrap();
-            if commit_branch.id() == commit_max.id() {
-                shorthand = Some(branch.shorthand().to_string());
-                break; 
+    /// Counts `oid` as a child of the parents the graph follows from it.
+    fn discover(&mut self, oid: Oid, repo: &Repository) -> Result<(), ExplorerError> {
+        if !self.discovered.insert(oid) {
+            return Ok(())
+        }
+        let commit = find_commit(repo, oid)?;
+        for parent in self.parent_ids(&commit) {
+            if self.painted.contains(&parent) {
+                self.skew_found = true;
+            }
+            *self.pending_children.entry(parent).or_insert(0) += 1;
+            if !self.discovered.contains(&parent) {
+                self.undiscovered.push((find_commit(repo, parent)?.time().seconds(), parent));
             }
-            i_branch = i_branch + 1;
         }
-        shorthand
+        Ok(())
     }
 
-    fn paint_split_commit<'a>(&self, parents_max: &Vec<Commit<'a>>, commits: &mut Vec<Commit<'a>>, max_index: usize, l: usize) -> (String, String) {
-        let mut paint_string_split = String::new();
-        let mut paint_string_join = String::new();
-        match parents_max.len() {
-            0 => {
-                paint_string_split.push_str(&format!("\n╽"));
-            },
-            1 => {
-                commits.insert(max_index, parents_max[0].clone());
-            },
-            2 => {
-                paint_string_split.push_str(&format!(
-                    "\n{}├{}─┐",
-                    String::from("│ ").repeat(max_index),
-                    String::from("──").repeat(l - (max_index + 1)),
-                ));
-                commits.insert(max_index, parents_max[0].clone());
-                commits.insert(max_index + 1, parents_max[1].clone());
-            },
-            _ => { panic!("AAHHH! There is a commit with more than 2 parents!!! I'm so scared... HINT: Use the case above and apply it to general") }
+    /// Discovers every commit reached so far that was committed at `time` or later.
+    fn discover_until(&mut self, time: i64, repo: &Repository) -> Result<(), ExplorerError> {
+        while let Some(&(next_time, oid)) = self.undiscovered.peek() {
+            if next_time < time {
+                break
+            }
+            self.undiscovered.pop();
+            self.discover(oid, repo)?;
         }
-        (paint_string_split, paint_string_join)
+        Ok(())
     }
 
-    fn paint_branch(
-        &mut self,
-        mut commits: Vec<Commit>,
-        mut output: Vec<GraphNode>,
-        branches: Vec<BranchData>,
-        repo: &Repository) -> Vec<GraphNode> {
-        let l = commits.len();
-
-        if self.abort(l) { return vec![] }
-
-        let max_index = self.find_max_index(commits.clone().into_iter().map(|c| c.time()).collect());
-
-        let commit_max = commits[max_index].clone();
-
-        // Figures out if the current commit has a branch name
-        let shorthand = self.short_hand_current_commit(&branches, repo, &commit_max);
-
-        let parents_max: Vec<Commit> = commit_max.parents().collect();
-
-        let mut paint_string = self.paint(l, max_index, parents_max.len() > 1);
+    /// Picks the entry of `frontier` to paint next, once every commit newer than it (give or take
+    /// the clock skew) is discovered so its children are known.
+    fn pick_next(&mut self, frontier: &[(usize, Oid)], commits: &[Commit], repo: &Repository) -> Result<usize, ExplorerError> {
+        let newest = commits.iter().map(|commit| commit.time().seconds()).max().unwrap_or(0);
+        let mut threshold = newest - CLOCK_SKEW_SECONDS;
+        loop {
+            self.discover_until(threshold, repo)?;
+            let i = self.pick_ready(frontier, commits);
+            let time = commits[i].time().seconds();
+            if time - CLOCK_SKEW_SECONDS >= threshold || self.undiscovered.is_empty() {
+                return Ok(i)
+            }
+            threshold = time - CLOCK_SKEW_SECONDS;
+        }
+    }
 
-        // SUBSTITUTE commit_max by all its parents inside the "commits" vector.
-        commits.remove(max_index);
-        let (paint_string_split, mut paint_string_join) = self.paint_split_commit(&parents_max, &mut commits, max_index, l);
+    /// Picks the entry of `frontier` to paint next among the discovered commits. Only commits
+    /// whose children are all painted are candidates, so clock skew can't put a parent above one
+    /// of its children.
+    fn pick_ready(&self, frontier: &[(usize, Oid)], commits: &[Commit]) -> usize {
+        let ready: Vec<usize> = (0..frontier.len())
+            .filter(|i| self.pending_children.get(&frontier[*i].1).copied().unwrap_or(0) == 0)
+            .collect();
+        let candidates = if ready.is_empty() { (0..frontier.len()).collect() } else { ready };
+
+        if self.order == GraphOrder::Topological {
+            if let Some(i) = candidates.iter().find(|i| Some(frontier[**i].0) == self.last_lane) {
+                return *i
+            }
+        }
 
-        let mut binding = commits.clone();
-        let (dedup, duplicates) = binding.partition_dedup_by(|a, b| a.id() == b.id());
+        let times: Vec<Time> = candidates
+            .iter()
+            .map(|i| match self.order {
+                GraphOrder::AuthorDate => commits[*i].author().when(),
+                _ => commits[*i].time(),
+            })
+            .collect();
+        candidates[self.find_max_index(times)]
+    }
 
-        let dupl_len = duplicates.len();
-        if dupl_len > 0 {
-            paint_string_join.push_str(&format!(
-                "\n{}├─{}┘",
-                String::from("│ ").repeat(l-(dupl_len + 1)),
-                String::from("──").repeat(dupl_len - 1)
-            ));
+    /// Restricts the graph to the commits reachable from `roots` but not from the selected stop
+    /// branch. Painting stops at the merge bases of the roots and the branch, so the graph
+    /// shows what the roots have that the branch doesn't even when the branch moved ahead.
+    fn set_stop_condition(&mut self, roots: &[Oid], repo: &Repository) -> Result<(), ExplorerError> {
+        self.visible = None;
+        self.merge_bases.clear();
+        let stop_oid = match self.get_selected_branch_oid() {
+            Some(stop_oid) => stop_oid,
+            None => return Ok(()),
+        };
+        for root in roots {
+            if let Ok(merge_base) = repo.merge_base(*root, stop_oid) {
+                self.merge_bases.insert(merge_base);
+            }
         }
-
-        if !paint_string_split.is_empty() && !paint_string_join.is_empty() {
-            paint_string.push_str(&format!("\n{}├─{}┤", String::new().repeat(0), String::new().repeat(0))) // TODO: // XXX: This will fail at any time, recreate a git history branch that will stress this condition
-        } else {
-            paint_string.push_str(&paint_string_split);
-            paint_string.push_str(&paint_string_join);
+        // Everything below a merge base is reachable from the branch, hiding the bases too ends
+        // the walk there rather than at the bottom of the history.
+        let mut revwalk = repo.revwalk()?;
+        for root in roots {
+            revwalk.push(*root)?;
         }
+        revwalk.hide(stop_oid)?;
+        for merge_base in self.merge_bases.iter() {
+            revwalk.hide(*merge_base)?;
+        }
+        self.visible = Some(revwalk.collect::<Result<HashSet<Oid>, _>>()?);
+        trace!("fn set_stop_condition {} merge bases", self.merge_bases.len());
+        Ok(())
+    }
 
-        self.maybe_set_abort(&commit_max);
-
-        let vec_str = self.paint_branch(dedup.to_vec(), vec![], branches, repo);
+    fn decorations_of(&self, commit: &Commit) -> Vec<Decoration> {
+        match self.decorations.get(&commit.id()) {
+            Some(decorations) => decorations.clone(),
+            None => vec![],
+        }
+    }
 
-        output.push(GraphNode { grapheme: paint_string, oid: commit_max.id(), branch_shorthand: shorthand, summary: commit_max.summary().unwrap().to_string() });
+    /// Paints up to `rows` commits, newest first, resuming from the commits waiting in `lanes`.
+    fn paint_rows(&mut self, rows: usize, repo: &Repository) -> Result<Vec<GraphNode>, ExplorerError> {
+        let mut output: Vec<GraphNode> = Vec::with_capacity(rows);
+
+        // Nothing painted after clock skew is found is kept, the graph is painted again.
+        while output.len() < rows && !self.lanes.is_empty() && !self.skew_found {
+            let frontier = self.lanes.frontier();
+            let commits: Vec<Commit> = frontier.iter().map(|(_, oid)| find_commit(repo, *oid)).collect::<Result<_, _>>()?;
+
+            let max_index = self.pick_next(&frontier, &commits, repo)?;
+            let (lane, _) = frontier[max_index];
+            let commit_max = &commits[max_index];
+
+            // Figures out which references point to the current commit
+            let decorations = self.decorations_of(commit_max);
+
+            self.painted.insert(commit_max.id());
+            let parents_max: Vec<Oid> = self.parent_ids(commit_max)
+                .into_iter()
+                .filter(|parent| !self.painted.contains(parent))
+                .collect();
+            for parent in parents_max.iter() {
+                if let Some(pending) = self.pending_children.get_mut(parent) {
+                    *pending = pending.saturating_sub(1);
+                }
+                // Whatever waits in a lane has its own parents counted.
+                self.discover(*parent, repo)?;
+            }
+            self.last_lane = Some(lane);
+
+            // SUBSTITUTE commit_max by all its parents inside the lanes.
+            let grapheme = self.lanes.advance(lane, &parents_max);
+
+            let merged_count = match self.order {
+                GraphOrder::FirstParent => self.merged_count(commit_max, repo)?,
+                _ => None,
+            };
+
+            output.push(GraphNode {
+                grapheme,
+                oid: commit_max.id(),
+                decorations,
+                summary: commit_max.summary().unwrap_or("").to_string(),
+                merged_count,
+                expanded: self.expanded.contains(&commit_max.id()),
+                merge_base: self.merge_bases.contains(&commit_max.id()),
+            });
+        }
 
-        [output, vec_str].concat()
+        Ok(output)
     }
 
-    pub fn paint_commit_track(&mut self, commit: Commit, branches: Vec<BranchData>, repo: &Repository) -> Vec<GraphNode> {
-    // pub fn paint_commit_track(&self, commit: Commit, branches: Vec<BranchData>, repo: &Repository) -> Vec<GraphNode> {
-        // let limit_stack = 1000; // Works fine
-
-        self.abort = false;
-        self.limit_stack = Some(LIMIT_STACK); // Works fine
-        self.paint_branch(vec![commit], vec![], branches, repo)
+    pub fn paint_commit_track(&mut self, commits: Vec<Commit>, repo: &Repository) -> Result<Vec<GraphNode>, ExplorerError> {
+        let roots: Vec<Oid> = commits.iter().map(|c| c.id()).collect();
+        self.set_stop_condition(&roots, repo)?;
+        self.pending_children.clear();
+        self.discovered.clear();
+        self.undiscovered.clear();
+        self.painted.clear();
+        self.skew_found = false;
+        for root in roots.iter() {
+            self.discover(*root, repo)?;
+        }
+        if self.discover_all {
+            self.discover_until(i64::MIN, repo)?;
+        }
+        self.last_lane = None;
+        self.lanes = Lanes::new(&roots);
+        let nodes = self.paint_rows(INITIAL_ROWS, repo)?;
+        if self.skew_found && !self.discover_all {
+            trace!("fn paint_commit_track clock skew found, painting again");
+            self.discover_all = true;
+            return self.paint_commit_track(commits, repo)
+        }
+        Ok(nodes)
     }
 }
 
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,487 @@
+use git2::{Oid, BranchType, Signature};
+
+use crate::explorer::branch_data::BranchData;
+use crate::explorer::graph_order::GraphOrder;
+use crate::explorer::head_state::HeadState;
+use crate::explorer::kernel::{Kernel, EXPAND_ROWS, INITIAL_ROWS};
+use crate::explorer::test_repo::TestRepo;
+
+/// Paints the whole graph from `root` and renders it the way the graph list shows it: the
+/// first line of every node followed by its summary, the connector rows on their own.
+fn graph(test_repo: &TestRepo, root: Oid) -> String {
+    graph_in_order(test_repo, root, GraphOrder::default())
+}
+
+fn graph_in_order(test_repo: &TestRepo, root: Oid, order: GraphOrder) -> String {
+    let mut kernel = Kernel::new(Some(root), vec![None], order);
+    kernel.run(&test_repo.repo).unwrap();
+    kernel.ensure_nodes(usize::MAX, &test_repo.repo).unwrap();
+
+    let mut lines: Vec<String> = vec![];
+    for node in kernel.nodes.iter() {
+        let mut graphemes = node.grapheme.split('\n');
+        lines.push(format!("{} {}", graphemes.next().unwrap(), node.summary));
+        lines.extend(graphemes.map(String::from));
+    }
+    lines.join("\n")
+}
+
+fn assert_graph(test_repo: &TestRepo, root: Oid, expected: &[&str]) {
+    assert_eq!(graph(test_repo, root), expected.join("\n"));
+}
+
+#[test]
+fn linear_history() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let b = test_repo.commit("B", &[a], 200);
+    let c = test_repo.commit("C", &[b], 300);
+
+    assert_graph(&test_repo, c, &[
+        "├● C",
+        "├● B",
+        "├● A",
+        "╽",
+    ]);
+}
+
+#[test]
+fn feature_branch_merged_back() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let f1 = test_repo.commit("F1", &[a], 200);
+    let c = test_repo.commit("C", &[a], 300);
+    let f2 = test_repo.commit("F2", &[f1], 400);
+    let m = test_repo.commit("M", &[c, f2], 500);
+
+    assert_graph(&test_repo, m, &[
+        "├● M",
+        "├─┐",
+        "│ ├● F2",
+        "├●│ C",
+        "│ ├● F1",
+        "├─┘",
+        "├● A",
+        "╽",
+    ]);
+}
+
+#[test]
+fn criss_cross_merges() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let b1 = test_repo.commit("B1", &[a], 200);
+    let b2 = test_repo.commit("B2", &[a], 300);
+    let m1 = test_repo.commit("M1", &[b1, b2], 400);
+    let m2 = test_repo.commit("M2", &[b2, b1], 500);
+    let top = test_repo.commit("TOP", &[m1, m2], 600);
+
+    assert_graph(&test_repo, top, &[
+        "├● TOP",
+        "├─┐",
+        "│ ├● M2",
+        "│ ├─┐",
+        "├●│ │ M1",
+        "└─┼─┤",
+        "  ├●│ B2",
+        "  │ ├● B1",
+        "  ├─┘",
+        "  ├● A",
+        "  ╽",
+    ]);
+}
+
+#[test]
+fn long_lived_parallel_branches() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let d1 = test_repo.commit("D1", &[a], 200);
+    let m1 = test_repo.commit("M1", &[a], 300);
+    let d2 = test_repo.commit("D2", &[d1], 400);
+    let m2 = test_repo.commit("M2", &[m1, d2], 500);
+    let d3 = test_repo.commit("D3", &[d2], 600);
+    let m3 = test_repo.commit("M3", &[m2], 700);
+    let m4 = test_repo.commit("M4", &[m3, d3], 800);
+
+    assert_graph(&test_repo, m4, &[
+        "├● M4",
+        "├─┐",
+        "├●│ M3",
+        "│ ├● D3",
+        "├●│ M2",
+        "├─┤",
+        "│ ├● D2",
+        "├●│ M1",
+        "│ ├● D1",
+        "├─┘",
+        "├● A",
+        "╽",
+    ]);
+}
+
+#[test]
+fn merge_of_merges() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let x = test_repo.commit("X", &[a], 200);
+    let y = test_repo.commit("Y", &[a], 300);
+    let mx = test_repo.commit("MX", &[a, x], 400);
+    let my = test_repo.commit("MY", &[a, y], 500);
+    let top = test_repo.commit("TOP", &[mx, my], 600);
+
+    assert_graph(&test_repo, top, &[
+        "├● TOP",
+        "├─┐",
+        "│ ├● MY",
+        "│ ├─┐",
+        "├●│ │ MX",
+        "├─┤ │",
+        "│ │ ├● Y",
+        "│ ├─┘",
+        "├●│ X",
+        "└─┤",
+        "  ├● A",
+        "  ╽",
+    ]);
+}
+
+#[test]
+fn octopus_merge() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let x1 = test_repo.commit("X1", &[a], 200);
+    let x2 = test_repo.commit("X2", &[a], 300);
+    let x3 = test_repo.commit("X3", &[a], 400);
+    let c = test_repo.commit("C", &[a], 450);
+    let octopus = test_repo.commit("OCTOPUS", &[c, x1, x2, x3], 500);
+
+    assert_graph(&test_repo, octopus, &[
+        "├● OCTOPUS",
+        "├─┬─┬─┐",
+        "├●│ │ │ C",
+        "│ │ │ ├● X3",
+        "├─┼─┼─┘",
+        "│ │ ├● X2",
+        "├─┼─┘",
+        "│ ├● X1",
+        "├─┘",
+        "├● A",
+        "╽",
+    ]);
+}
+
+#[test]
+fn join_and_fork_on_the_same_row() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let p = test_repo.commit("P", &[a], 200);
+    let q = test_repo.commit("Q", &[a], 300);
+    let r = test_repo.commit("R", &[a], 350);
+    let octopus = test_repo.commit("OCTOPUS", &[p, q, r], 400);
+    let x = test_repo.commit("X", &[p], 500);
+    let top = test_repo.commit("TOP", &[x, octopus], 600);
+
+    assert_graph(&test_repo, top, &[
+        "├● TOP",
+        "├─┐",
+        "├●│ X",
+        "│ ├● OCTOPUS",
+        "├─┼─┐",
+        "│ │ ├● R",
+        "│ ├●│ Q",
+        "│ └─┤",
+        "├●  │ P",
+        "└───┤",
+        "    ├● A",
+        "    ╽",
+    ]);
+}
+
+#[test]
+fn clock_skew_never_paints_a_parent_above_its_child() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    // P claims to be newer than all of its descendants.
+    let p = test_repo.commit("P", &[a], 900);
+    let y = test_repo.commit("Y", &[p], 200);
+    let x = test_repo.commit("X", &[p], 600);
+    let top = test_repo.commit("TOP", &[x, y], 700);
+
+    assert_graph(&test_repo, top, &[
+        "├● TOP",
+        "├─┐",
+        "├●│ X",
+        "│ ├● Y",
+        "├─┘",
+        "├● P",
+        "├● A",
+        "╽",
+    ]);
+}
+
+#[test]
+fn author_date_order() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    // Rebased: authored long ago, committed recently.
+    let rebased = test_repo.commit_at("REBASED", &[a], 150, 500);
+    let fresh = test_repo.commit_at("FRESH", &[a], 400, 400);
+    let top = test_repo.commit("TOP", &[rebased, fresh], 600);
+
+    assert_eq!(graph_in_order(&test_repo, top, GraphOrder::CommitterDate), [
+        "├● TOP",
+        "├─┐",
+        "├●│ REBASED",
+        "│ ├● FRESH",
+        "├─┘",
+        "├● A",
+        "╽",
+    ].join("\n"));
+    assert_eq!(graph_in_order(&test_repo, top, GraphOrder::AuthorDate), [
+        "├● TOP",
+        "├─┐",
+        "│ ├● FRESH",
+        "├●│ REBASED",
+        "└─┤",
+        "  ├● A",
+        "  ╽",
+    ].join("\n"));
+}
+
+#[test]
+fn topological_order_keeps_lines_of_history_together() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let f1 = test_repo.commit("F1", &[a], 200);
+    let m1 = test_repo.commit("M1", &[a], 300);
+    let f2 = test_repo.commit("F2", &[f1], 400);
+    let m2 = test_repo.commit("M2", &[m1], 500);
+    let top = test_repo.commit("TOP", &[m2, f2], 600);
+
+    assert_eq!(graph_in_order(&test_repo, top, GraphOrder::Topological), [
+        "├● TOP",
+        "├─┐",
+        "├●│ M2",
+        "├●│ M1",
+        "│ ├● F2",
+        "│ ├● F1",
+        "├─┘",
+        "├● A",
+        "╽",
+    ].join("\n"));
+}
+
+#[test]
+fn first_parent_order_skips_merged_branches() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let f1 = test_repo.commit("F1", &[a], 200);
+    let c = test_repo.commit("C", &[a], 300);
+    let m = test_repo.commit("M", &[c, f1], 400);
+
+    assert_eq!(graph_in_order(&test_repo, m, GraphOrder::FirstParent), [
+        "├● M",
+        "├● C",
+        "├● A",
+        "╽",
+    ].join("\n"));
+}
+
+#[test]
+fn first_parent_merges_expand_in_place() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let f1 = test_repo.commit("F1", &[a], 200);
+    let f2 = test_repo.commit("F2", &[f1], 250);
+    let c = test_repo.commit("C", &[a], 300);
+    let m = test_repo.commit("M", &[c, f2], 400);
+    let d = test_repo.commit("D", &[m], 500);
+
+    let mut kernel = Kernel::new(Some(d), vec![None], GraphOrder::FirstParent);
+    kernel.run(&test_repo.repo).unwrap();
+    assert_eq!(kernel.nodes[1].merged_count, Some(2));
+    assert!(!kernel.nodes[1].expanded);
+    assert_eq!(kernel.get_nodes_len(), 4);
+
+    kernel.toggle_expanded(m, &test_repo.repo).unwrap();
+    kernel.ensure_nodes(usize::MAX, &test_repo.repo).unwrap();
+    assert!(kernel.nodes[1].expanded);
+    let summaries: Vec<&str> = kernel.nodes.iter().map(|node| node.summary.as_str()).collect();
+    assert_eq!(summaries, ["D", "M", "C", "F2", "F1", "A"]);
+
+    kernel.toggle_expanded(m, &test_repo.repo).unwrap();
+    assert_eq!(kernel.get_nodes_len(), 4);
+}
+
+#[test]
+fn stop_condition_stops_at_the_merge_base_of_a_diverged_branch() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let b = test_repo.commit("B", &[a], 200);
+    let f1 = test_repo.commit("F1", &[b], 300);
+    let c = test_repo.commit("C", &[b], 400);
+    let f2 = test_repo.commit("F2", &[f1], 500);
+    let d = test_repo.commit("D", &[c], 600);
+    test_repo.branch("main", d);
+    let main = BranchData::new((test_repo.repo.find_branch("main", BranchType::Local).unwrap(), BranchType::Local)).unwrap();
+
+    let mut kernel = Kernel::new(Some(f2), vec![Some(main)], GraphOrder::default());
+    kernel.run(&test_repo.repo).unwrap();
+    let summaries: Vec<&str> = kernel.nodes.iter().map(|node| node.summary.as_str()).collect();
+    assert_eq!(summaries, ["F2", "F1", "B"]);
+    assert!(kernel.nodes[2].merge_base);
+    assert!(kernel.is_exhausted());
+}
+
+#[test]
+fn decorations_come_from_loose_and_packed_refs() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let b = test_repo.commit("B", &[a], 200);
+    test_repo.branch("main", b);
+    test_repo.branch("old", a);
+    let signature = Signature::now("Tester", "tester@example.com").unwrap();
+    test_repo.repo.tag("v1", &test_repo.repo.find_object(a, None).unwrap(), &signature, "v1", false).unwrap();
+    test_repo.repo.reference("refs/tags/light", b, false, "").unwrap();
+    std::fs::write(
+        test_repo.path.join(".git/packed-refs"),
+        format!("# pack-refs with: peeled fully-peeled sorted \n{} refs/remotes/origin/main\n", a),
+    ).unwrap();
+
+    let mut kernel = Kernel::new(Some(b), vec![None], GraphOrder::default());
+    kernel.run(&test_repo.repo).unwrap();
+    let decorations: Vec<Vec<String>> = kernel.nodes.iter()
+        .map(|node| node.decorations.iter().map(|decoration| decoration.to_string()).collect())
+        .collect();
+    assert_eq!(decorations, [
+        vec!["main", "tag: light"],
+        vec!["old", "origin/main", "tag: v1"],
+    ]);
+}
+
+#[test]
+fn unborn_head_paints_an_empty_graph() {
+    let test_repo = TestRepo::new();
+    test_repo.repo.set_head("refs/heads/main").unwrap();
+    assert_eq!(HeadState::new(&test_repo.repo), HeadState::Unborn(String::from("main")));
+    assert_eq!(HeadState::new(&test_repo.repo).to_string(), "unborn branch main");
+
+    let mut kernel = Kernel::new(None, vec![None], GraphOrder::default());
+    kernel.run(&test_repo.repo).unwrap();
+    kernel.ensure_nodes(usize::MAX, &test_repo.repo).unwrap();
+    assert_eq!(kernel.get_nodes_len(), 0);
+    assert!(kernel.is_exhausted());
+}
+
+#[test]
+fn detached_head_paints_from_the_detached_commit() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let b = test_repo.commit("B", &[a], 200);
+    test_repo.branch("main", b);
+    test_repo.repo.set_head_detached(a).unwrap();
+    assert_eq!(HeadState::new(&test_repo.repo), HeadState::Detached(a));
+    assert_eq!(HeadState::new(&test_repo.repo).to_string(), format!("detached at {}", &a.to_string()[..7]));
+
+    let mut kernel = Kernel::new(None, vec![None], GraphOrder::default());
+    kernel.run(&test_repo.repo).unwrap();
+    let summaries: Vec<&str> = kernel.nodes.iter().map(|node| node.summary.as_str()).collect();
+    assert_eq!(summaries, ["A"]);
+}
+
+#[test]
+fn painting_the_first_rows_doesnt_walk_the_whole_history() {
+    const DAY: i64 = 24 * 60 * 60;
+    let test_repo = TestRepo::new();
+    let mut tip = test_repo.commit("0", &[], DAY);
+    let mut side = tip;
+    for i in 1..400 {
+        tip = test_repo.commit(&i.to_string(), &[tip], (i + 1) * DAY);
+        // A branch forked and merged back every ten commits.
+        if i % 10 == 5 {
+            side = tip;
+        } else if i % 10 == 0 {
+            side = test_repo.commit(&format!("side {}", i), &[side], (i + 1) * DAY - 1);
+            tip = test_repo.commit(&format!("merge {}", i), &[tip, side], (i + 1) * DAY + 1);
+        }
+    }
+
+    let mut kernel = Kernel::new(Some(tip), vec![None], GraphOrder::default());
+    kernel.run(&test_repo.repo).unwrap();
+    assert_eq!(kernel.get_nodes_len(), INITIAL_ROWS);
+    assert!(kernel.discovered.len() < INITIAL_ROWS + 10, "discovered {} commits", kernel.discovered.len());
+
+    kernel.ensure_nodes(INITIAL_ROWS + EXPAND_ROWS, &test_repo.repo).unwrap();
+    assert!(kernel.discovered.len() < INITIAL_ROWS + EXPAND_ROWS + 10);
+}
+
+#[test]
+fn merged_count_matches_the_rows_expanding_shows() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let h = test_repo.commit("H", &[a], 150);
+    let f1 = test_repo.commit("F1", &[a], 200);
+    // The side branch merged something of its own, expanding only follows its first parents.
+    let g = test_repo.commit("G", &[f1, h], 250);
+    let f2 = test_repo.commit("F2", &[g], 275);
+    let c = test_repo.commit("C", &[a], 300);
+    let m = test_repo.commit("M", &[c, f2], 400);
+    let d = test_repo.commit("D", &[m], 500);
+
+    let mut kernel = Kernel::new(Some(d), vec![None], GraphOrder::FirstParent);
+    kernel.run(&test_repo.repo).unwrap();
+    assert_eq!(kernel.nodes[1].merged_count, Some(3));
+    let collapsed = kernel.get_nodes_len();
+
+    kernel.toggle_expanded(m, &test_repo.repo).unwrap();
+    kernel.ensure_nodes(usize::MAX, &test_repo.repo).unwrap();
+    let summaries: Vec<&str> = kernel.nodes.iter().map(|node| node.summary.as_str()).collect();
+    assert_eq!(summaries, ["D", "M", "C", "F2", "G", "F1", "A"]);
+    assert_eq!(kernel.get_nodes_len() - collapsed, 3);
+    // G is a merge too, its own count is of H.
+    assert_eq!(kernel.nodes[4].merged_count, Some(1));
+}
+
+#[test]
+fn stop_condition_only_walks_down_to_the_merge_base() {
+    let test_repo = TestRepo::new();
+    let mut base = test_repo.commit("0", &[], 100);
+    for i in 1..200 {
+        base = test_repo.commit(&i.to_string(), &[base], 100 + i);
+    }
+    let f1 = test_repo.commit("F1", &[base], 1000);
+    let c = test_repo.commit("C", &[base], 1100);
+    let f2 = test_repo.commit("F2", &[f1], 1200);
+    test_repo.branch("main", c);
+    let main = BranchData::new((test_repo.repo.find_branch("main", BranchType::Local).unwrap(), BranchType::Local)).unwrap();
+
+    let mut kernel = Kernel::new(Some(f2), vec![Some(main)], GraphOrder::default());
+    kernel.run(&test_repo.repo).unwrap();
+    let summaries: Vec<&str> = kernel.nodes.iter().map(|node| node.summary.as_str()).collect();
+    assert_eq!(summaries, ["F2", "F1", "199"]);
+    assert_eq!(kernel.visible.as_ref().map(|visible| visible.len()), Some(2));
+}
+
+#[test]
+fn clock_skew_deeper_than_discovery_still_paints_children_first() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let p = test_repo.commit("P", &[a], 100000);
+    // Ten hours older than its parent, it isn't discovered before P is ready.
+    let c = test_repo.commit("C", &[p], 64000);
+    let y = test_repo.commit("Y", &[c], 65000);
+    let x = test_repo.commit("X", &[p], 110000);
+    let top = test_repo.commit("TOP", &[x, y], 120000);
+
+    assert_graph(&test_repo, top, &[
+        "├● TOP",
+        "├─┐",
+        "├●│ X",
+        "│ ├● Y",
+        "│ ├● C",
+        "├─┘",
+        "├● P",
+        "├● A",
+        "╽",
+    ]);
+}
@@ -0,0 +1,149 @@
+use git2::Oid;
+
+const LANE: &str = "│ ";
+const EMPTY: &str = "  ";
+const COMMIT: &str = "├●";
+
+/// Column model of the graph, in the spirit of git's `graph.c`: every column holds the commit
+/// it is waiting for, or nothing when the lane ended. Free columns are reused by new lanes so
+/// the other lanes never have to shift sideways.
+pub struct Lanes {
+    columns: Vec<Option<Oid>>,
+}
+
+impl Lanes {
+    pub fn new(roots: &[Oid]) -> Self {
+        let mut columns: Vec<Option<Oid>> = vec![];
+        for root in roots {
+            if !columns.contains(&Some(*root)) {
+                columns.push(Some(*root));
+            }
+        }
+        Self { columns }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.iter().all(|column| column.is_none())
+    }
+
+    /// Commits waiting to be painted, with the lane each one is in.
+    pub fn frontier(&self) -> Vec<(usize, Oid)> {
+        self.columns
+            .iter()
+            .enumerate()
+            .filter_map(|(i, column)| column.map(|oid| (i, oid)))
+            .collect()
+    }
+
+    fn position(&self, oid: Oid) -> Option<usize> {
+        self.columns.iter().position(|column| *column == Some(oid))
+    }
+
+    fn cells(&self) -> Vec<&'static str> {
+        self.columns
+            .iter()
+            .map(|column| if column.is_some() { LANE } else { EMPTY })
+            .collect()
+    }
+
+    fn render(cells: Vec<&str>) -> String {
+        cells.concat().trim_end().to_string()
+    }
+
+    /// Paints the commit waiting in `lane` and replaces it with its `parents`. Returns the
+    /// commit row followed by the connector row, separated by `\n`.
+    pub fn advance(&mut self, lane: usize, parents: &[Oid]) -> String {
+        let mut cells = self.cells();
+        cells[lane] = COMMIT;
+        let mut rows = vec![Self::render(cells)];
+
+        // Parents already waiting in another lane get joined, the others open a lane. The lane
+        // of the commit goes on with the first parent that opens one.
+        let mut targets: Vec<Target> = vec![];
+        self.columns[lane] = None;
+        for parent in parents {
+            match self.position(*parent) {
+                Some(j) => targets.push(Target::Join(j)),
+                None if self.columns[lane].is_none() => self.columns[lane] = Some(*parent),
+                None => {
+                    let free = self.columns.iter().skip(lane + 1).position(|column| column.is_none());
+                    let t = match free {
+                        Some(free) => lane + 1 + free,
+                        None => {
+                            self.columns.push(None);
+                            self.columns.len() - 1
+                        }
+                    };
+                    self.columns[t] = Some(*parent);
+                    targets.push(Target::Split(t));
+                }
+            }
+        }
+
+        if !targets.is_empty() {
+            rows.push(self.connector_row(lane, &targets));
+        } else if parents.is_empty() {
+            let mut cells = self.cells();
+            cells[lane] = "╽";
+            rows.push(Self::render(cells));
+        }
+
+        while self.columns.last() == Some(&None) {
+            self.columns.pop();
+        }
+        rows.join("\n")
+    }
+
+    /// One row connecting the commit in `lane` to every lane its parents continue in, e.g.
+    /// `├─┐` for a merge, `├─┘` for a branch joining back, `├─┴─┐` when both happen at once or
+    /// `├─┬─┐` for an octopus merge. Lanes crossed on the way are drawn as `┼`.
+    fn connector_row(&self, lane: usize, targets: &[Target]) -> String {
+        let columns: Vec<usize> = targets.iter().map(|target| target.column()).collect();
+        let first = (*columns.iter().min().unwrap()).min(lane);
+        let last = (*columns.iter().max().unwrap()).max(lane);
+
+        let mut cells: Vec<String> = self.cells().into_iter().map(String::from).collect();
+        for (i, cell) in cells.iter_mut().enumerate().take(last + 1).skip(first) {
+            let glyph = if i == lane {
+                match (self.columns[lane].is_some(), lane == first, lane == last) {
+                    (true, true, _) => '├',
+                    (true, _, true) => '┤',
+                    (true, _, _) => '┼',
+                    (false, true, _) => '└',
+                    (false, _, true) => '┘',
+                    (false, _, _) => '┴',
+                }
+            } else {
+                match targets.iter().find(|target| target.column() == i) {
+                    Some(Target::Split(_)) if i == first => '┌',
+                    Some(Target::Split(_)) if i == last => '┐',
+                    Some(Target::Split(_)) => '┬',
+                    Some(Target::Join(_)) if i == first => '├',
+                    Some(Target::Join(_)) if i == last => '┤',
+                    Some(Target::Join(_)) => '┼',
+                    None if self.columns[i].is_some() => '┼',
+                    None => '─',
+                }
+            };
+            let filler = if i < last { '─' } else { ' ' };
+            *cell = format!("{}{}", glyph, filler);
+        }
+        cells.concat().trim_end().to_string()
+    }
+}
+
+/// Where an edge from a commit to one of its parents ends on the connector row.
+enum Target {
+    // The parent opens a new lane in this column.
+    Split(usize),
+    // The parent was already waiting in this column.
+    Join(usize),
+}
+
+impl Target {
+    fn column(&self) -> usize {
+        match self {
+            Target::Split(column) | Target::Join(column) => *column,
+        }
+    }
+}
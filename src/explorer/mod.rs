@@ -1,44 +1,122 @@
-use log::{debug, error, info, trace, warn, LevelFilter, SetLoggerError};
-use git2::{Repository, Commit, Oid, Time, Branches, Branch, BranchType};
-use tui::{
-    style::{Color, Style},
-    text::Span,
-};
+use git2::{Oid, BranchType};
+use tui::text::Span;
 
-use std::process;
+use std::cell::{OnceCell, RefCell};
+use std::rc::Rc;
 
-use crate::utils::short_id;
 use crate::explorer::graph_node::GraphNode;
 use crate::explorer::parsed_diff::ParsedDiff;
-use crate::explorer::git_wrapper::GitWrapper;
-use crate::explorer::kernel::Kernel;
+use crate::explorer::parsed_diff::syntax::SyntaxHighlighter;
+use crate::explorer::git_wrapper::{GitWrapper, find_commit};
+use crate::explorer::kernel::{Kernel, EXPAND_ROWS};
+use crate::explorer::child_index::ChildIndex;
 
-use self::branch_data::BranchData;
+use self::branch_data::{AheadBehindCache, BranchData};
+use self::graph_order::GraphOrder;
+use self::diff_base::DiffBase;
+use self::head_state::HeadState;
+use self::error::ExplorerError;
 
 pub mod graph_node;
 pub mod parsed_diff;
 pub mod branch_data;
 pub mod git_wrapper;
 pub mod kernel;
+pub mod child_index;
+pub mod lanes;
+pub mod graph_order;
+pub mod diff_base;
+pub mod decoration;
+pub mod error;
+pub mod head_state;
+#[cfg(test)]
+pub mod test_repo;
+#[cfg(test)]
+mod tests;
+
+/// What a diff is built from, highlighting included.
+#[derive(Clone, Copy, PartialEq)]
+enum DiffKey {
+    /// A commit against its parents, see `diff_commit`.
+    Base(Oid, DiffBase, bool),
+    /// A commit against any other, see `diff_commit_by_id`.
+    Commits(Oid, Option<Oid>, bool),
+}
 
 pub struct GitExplorer {
     kernel: Kernel,
     git_wrapper: GitWrapper,
+    child_index: Option<ChildIndex>,
+    // Every local and remote branch, loaded on every run. Ahead/behind counts are only computed
+    // for the branches shown, see `with_ahead_behind`, and kept until the next run.
+    branches: Vec<BranchData>,
+    ahead_behind: RefCell<AheadBehindCache>,
+    syntax_highlighting: bool,
+    // Loaded on the first highlighted diff.
+    syntax_highlighter: OnceCell<SyntaxHighlighter>,
+    // The last diff built and what it was built from: the UI asks again on every redraw.
+    diff_cache: RefCell<Option<(DiffKey, Rc<ParsedDiff>)>>,
 }
 
 impl<'a> GitExplorer {
-    pub fn new(path: Option<String>, root_oid: Option<Oid>, stop_condition: Option<BranchData>) -> Self {
+    /// Opens the repository (see `git_wrapper::open_repository`). This is the only handle on it,
+    /// the UI goes through the explorer for everything it shows.
+    pub fn new(path: Option<String>, order: GraphOrder) -> Result<Self, ExplorerError> {
 
-        let git_wrapper = GitWrapper::new(path);
+        let git_wrapper = GitWrapper::new(path)?;
 
-        let stop_conditions = git_wrapper.branches_data(stop_condition);
+        let stop_conditions = git_wrapper.branches_data(None)?;
 
-        let kernel = Kernel::new(root_oid, stop_conditions);
+        let kernel = Kernel::new(None, stop_conditions, order);
 
-        Self {
+        Ok(Self {
             git_wrapper,
             kernel,
+            child_index: None,
+            branches: vec![],
+            ahead_behind: RefCell::new(AheadBehindCache::default()),
+            syntax_highlighting: true,
+            syntax_highlighter: OnceCell::new(),
+            diff_cache: RefCell::new(None),
+        })
+    }
+
+    /// Starts the graph at `revspec`, anything `git rev-parse` understands, instead of HEAD.
+    pub fn set_root(&mut self, revspec: &str) -> Result<Oid, ExplorerError> {
+        let oid = self.git_wrapper.repo
+            .revparse_single(revspec)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|source| ExplorerError::BadRevision { revspec: revspec.to_string(), source })?
+            .id();
+        self.kernel.set_root(oid);
+        Ok(oid)
+    }
+
+    /// Stops painting at the first local branch whose name contains `filter`, if any.
+    pub fn set_stop_condition(&mut self, filter: &str) -> Result<(), ExplorerError> {
+        let mut stop_condition = None;
+        for branch in self.git_wrapper.branches(Some(BranchType::Local))? {
+            let branch_data = BranchData::new(branch?)?;
+            if branch_data.shorthand().contains(filter) {
+                stop_condition = Some(branch_data);
+                break
+            }
         }
+        let stop_conditions = self.git_wrapper.branches_data(stop_condition)?;
+        self.kernel.set_stop_conditions(stop_conditions);
+        Ok(())
+    }
+
+    fn load_branches(&mut self) -> Result<(), ExplorerError> {
+        let worktrees = self.git_wrapper.checked_out_branches();
+        let mut branches = vec![];
+        for branch in self.git_wrapper.branches(None)? {
+            branches.push(BranchData::new(branch?)?.with_worktree(&worktrees));
+        }
+        self.branches = branches;
+        // HEAD may have moved since.
+        *self.ahead_behind.get_mut() = AheadBehindCache::default();
+        Ok(())
     }
 
     pub fn stop_branch(&mut self, i: Option<usize>) {
@@ -46,16 +124,100 @@ impl<'a> GitExplorer {
     }
 
     // MAYBE USE MACROS HERE?
-    pub fn run(&mut self) {
+    pub fn run(&mut self) -> Result<(), ExplorerError> {
+        self.load_branches()?;
         self.kernel.run(&self.git_wrapper.repo)
     }
 
-    pub fn update_graph(&mut self, i: isize) {
+    pub fn update_graph(&mut self, i: isize) -> Result<(), ExplorerError> {
         self.kernel.update_graph(i, &self.git_wrapper.repo)
     }
 
-    pub fn diff_commit_by_id(&self, commit_1: Commit, commit_2: Option<Oid>) -> ParsedDiff {
-        self.kernel.diff_commit_by_id(commit_1, commit_2, &self.git_wrapper.repo)
+    /// Children of `oid` among every commit reachable from a reference, newest first. The child
+    /// index is built on first use because it walks the whole repository.
+    pub fn children(&mut self, oid: Oid) -> Result<Vec<Oid>, ExplorerError> {
+        let child_index = match self.child_index.take() {
+            Some(child_index) => child_index,
+            None => ChildIndex::new(&self.git_wrapper.repo)?,
+        };
+        let children = child_index.children(oid).to_vec();
+        self.child_index = Some(child_index);
+        Ok(children)
+    }
+
+    /// Makes sure `oid` is painted, adding it as a new root when it's newer than the graph, and
+    /// returns its row.
+    pub fn step_to_child(&mut self, oid: Oid) -> Result<Option<usize>, ExplorerError> {
+        if self.kernel.find_node(oid).is_none() {
+            self.kernel.add_root(oid, &self.git_wrapper.repo)?;
+        }
+        loop {
+            match self.kernel.find_node(oid) {
+                Some(i) => return Ok(Some(i)),
+                None if self.kernel.is_exhausted() => return Ok(None),
+                None => {
+                    let len = self.kernel.get_nodes_len();
+                    self.kernel.ensure_nodes(len + EXPAND_ROWS, &self.git_wrapper.repo)?;
+                }
+            }
+        }
+    }
+
+    pub fn parent_count(&self, oid: Oid) -> usize {
+        match self.git_wrapper.repo.find_commit(oid) {
+            Ok(commit) => commit.parent_count(),
+            Err(_) => 0,
+        }
+    }
+
+    pub fn commit_summary(&self, oid: Oid) -> String {
+        match self.git_wrapper.repo.find_commit(oid) {
+            Ok(commit) => commit.summary().unwrap_or("").to_string(),
+            Err(_) => String::new(),
+        }
+    }
+
+    pub fn head_state(&self) -> HeadState {
+        self.git_wrapper.head_state()
+    }
+
+    pub fn order(&self) -> GraphOrder {
+        self.kernel.order()
+    }
+
+    pub fn set_order(&mut self, order: GraphOrder) -> Result<(), ExplorerError> {
+        self.kernel.set_order(order, &self.git_wrapper.repo)
+    }
+
+    pub fn toggle_expanded(&mut self, oid: Oid) -> Result<(), ExplorerError> {
+        self.kernel.toggle_expanded(oid, &self.git_wrapper.repo)
+    }
+
+    pub fn ensure_nodes(&mut self, len: usize) -> Result<(), ExplorerError> {
+        self.kernel.ensure_nodes(len, &self.git_wrapper.repo)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.kernel.is_exhausted()
+    }
+
+    pub fn diff_commit_by_id(&self, oid_1: Oid, commit_2: Option<Oid>) -> Result<Rc<ParsedDiff>, ExplorerError> {
+        self.cached_diff(DiffKey::Commits(oid_1, commit_2, self.syntax_highlighting), || {
+            let commit_1 = find_commit(&self.git_wrapper.repo, oid_1)?;
+            self.kernel.diff_commit_by_id(commit_1, commit_2, &self.git_wrapper.repo)
+        })
+    }
+
+    /// Turns syntax highlighting of diffs off, it's slow on huge ones, or back on.
+    pub fn toggle_syntax_highlighting(&mut self) {
+        self.syntax_highlighting = !self.syntax_highlighting;
+    }
+
+    fn highlight(&self, mut parsed_diff: ParsedDiff) -> ParsedDiff {
+        if self.syntax_highlighting {
+            parsed_diff.highlight_syntax(self.syntax_highlighter.get_or_init(SyntaxHighlighter::new));
+        }
+        parsed_diff
     }
 
     pub fn get_selected_branch_oid(&self) -> Option<Oid> {
@@ -64,22 +226,49 @@ impl<'a> GitExplorer {
     pub fn branches_strings(&self) -> Vec<Span> {
         self.kernel.branches_strings()
     }
+    /// `branch_data` with its ahead/behind counts, for a branch about to be shown.
+    pub fn with_ahead_behind(&self, branch_data: BranchData) -> BranchData {
+        branch_data.with_ahead_behind(&self.git_wrapper.repo, &mut self.ahead_behind.borrow_mut())
+    }
+
     pub fn branches(&self, branch_type: Option<BranchType>, string_filter: Option<&String>) -> Vec<BranchData> {
         let mut branches = vec![];
-        for branch in self.git_wrapper.branches(branch_type).unwrap() {
-            let branch_data = BranchData::new(branch);
+        for branch_data in self.branches.iter() {
+            if let Some(branch_type) = branch_type {
+                if !branch_data.is_type(branch_type) { continue }
+            }
             match string_filter.clone() {
                 Some(string_filter) => {
-                    if branch_data.shorthand().contains(string_filter) { branches.push(branch_data); }
+                    if branch_data.shorthand().contains(string_filter) { branches.push(branch_data.clone()); }
                 },
-                None => branches.push(branch_data)
+                None => branches.push(branch_data.clone())
             }
-            
+
         }
         branches
     }
-    pub fn diff_commit(&self, commit_1: Commit, i_2: usize) -> ParsedDiff {
-        self.kernel.diff_commit(commit_1, i_2, &self.git_wrapper.repo)
+    /// `oid` against one of its parents, or all of them for a combined diff. Asking for the same
+    /// diff again returns it without rebuilding it, a commit's diff never changes.
+    pub fn diff_commit(&self, oid: Oid, base: DiffBase) -> Result<Rc<ParsedDiff>, ExplorerError> {
+        self.cached_diff(DiffKey::Base(oid, base, self.syntax_highlighting), || {
+            let commit = find_commit(&self.git_wrapper.repo, oid)?;
+            match base {
+                DiffBase::Parent(parent) => ParsedDiff::against_parent(commit, parent, &self.git_wrapper.repo),
+                DiffBase::Combined => ParsedDiff::combined(commit, &self.git_wrapper.repo),
+            }
+        })
+    }
+
+    /// The diff for `key`, built and highlighted by `build` unless it's the last one asked for.
+    fn cached_diff(&self, key: DiffKey, build: impl FnOnce() -> Result<ParsedDiff, ExplorerError>) -> Result<Rc<ParsedDiff>, ExplorerError> {
+        if let Some((cached_key, parsed_diff)) = self.diff_cache.borrow().as_ref() {
+            if *cached_key == key {
+                return Ok(Rc::clone(parsed_diff))
+            }
+        }
+        let parsed_diff = Rc::new(self.highlight(build()?));
+        *self.diff_cache.borrow_mut() = Some((key, Rc::clone(&parsed_diff)));
+        Ok(parsed_diff)
     }
     pub fn get_node_id(&self, i: usize) -> Option<Oid> {
         // self.kernel.get_node_id(i).clone()
@@ -1,192 +1,410 @@
-use git2::{Repository, Commit, Oid, DiffHunk, DiffLine, DiffDelta};
-use log::{error, info, trace};
-use tui::{
-    style::{Color, Style},
-    text::{Span, Spans},
-};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use git2::{Repository, Commit, Oid, Diff, DiffLine, DiffDelta, DiffFile, DiffFindOptions, DiffFlags, DiffFormat, Delta, FileMode, Patch, Tree};
 use crate::utils::short_id;
+use crate::explorer::error::ExplorerError;
+use crate::explorer::git_wrapper::find_commit;
 
-pub struct ParsedDiff<'a> {
-    commit_1_oid: Oid,
-    commit_2_oid: Option<Oid>,
-    pub test_lines: Vec<Spans<'a>>,
+/// A commit and its changes against another commit, as plain data. How it's drawn is up to the
+/// caller, see `ui::diff` for the terminal.
+pub struct ParsedDiff {
+    pub commit: CommitDetails,
+    /// The commit the diff is taken against. None for a root commit, which is compared with the
+    /// empty tree, and for a combined diff, which is compared with every parent.
+    pub compared: Option<Oid>,
+    pub files: Vec<FileDiff>,
+    /// Lines added and removed across all files.
+    pub insertions: usize,
+    pub deletions: usize,
 }
 
-pub struct MyDiffLine<'a>(DiffLine<'a>);
+pub struct CommitDetails {
+    pub id: Oid,
+    pub parents: Vec<Oid>,
+    pub message: String,
+    pub author: String,
+    pub committer: String,
+}
 
-impl From<MyDiffLine<'_>> for String {
-    fn from(line: MyDiffLine) -> String {
-        format!("{}:{}{}",
-            line.0.new_lineno().unwrap_or_else(|| line.0.old_lineno().unwrap()),
-            line.0.origin().to_string(),
-            String::from_utf8(line.0.content().to_vec()).unwrap()
-        )
-    }
+pub struct FileDiff {
+    pub status: Delta,
+    /// `/dev/null` for an added file.
+    pub old_path: String,
+    /// `/dev/null` for a deleted file.
+    pub new_path: String,
+    pub old_mode: FileMode,
+    pub new_mode: FileMode,
+    /// Percentage, for renamed and copied files.
+    pub similarity: Option<u16>,
+    pub content: FileContent,
+    /// Lines added and removed, none for a binary file.
+    pub insertions: usize,
+    pub deletions: usize,
 }
 
-impl<'a> From<MyDiffLine<'_>> for Spans<'a> {
-    fn from(line: MyDiffLine) -> Spans<'a> {
-        let s = format!("{}:{}{}",
-            line.0.new_lineno().unwrap_or_else(|| line.0.old_lineno().unwrap()),
-            line.0.origin().to_string(),
-            String::from_utf8(line.0.content().to_vec()).unwrap()
-        );
-        let style = match line.0.origin() {
-            ' ' => Style::default().fg(Color::White),
-            '+' => Style::default().fg(Color::Green),
-            '-' => Style::default().fg(Color::Red),
-            _ => Style::default().fg(Color::White),
-        };
-        Spans::from(vec![Span::styled(s, style)])
+pub enum FileContent {
+    Text(Vec<Hunk>),
+    Binary { old_size: u64, new_size: u64 },
+    /// The "Subproject commit" lines say nothing more than the two ids.
+    Submodule { old_id: Oid, new_id: Oid },
+}
+
+pub struct Hunk {
+    /// `@@ -1,3 +1,4 @@` and whatever git found as the enclosing function.
+    pub header: String,
+    pub lines: Vec<Line>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineOrigin {
+    Context,
+    Addition,
+    Deletion,
+    /// "\ No newline at end of file", after the line it's about.
+    NoNewline,
+}
+
+pub struct Line {
+    pub origin: LineOrigin,
+    pub old_lineno: Option<u32>,
+    pub new_lineno: Option<u32>,
+    /// With its line ending. Text that isn't UTF-8 is kept with replacement characters rather
+    /// than not at all.
+    pub content: String,
+    /// Origin against each parent in a combined diff, empty otherwise.
+    pub parents: Vec<LineOrigin>,
+    /// Byte ranges of `content` that changed from the line it replaces, see `word_diff`.
+    pub emphasis: Vec<Range<usize>>,
+    /// Foreground color of ranges of `content` after its language, see `syntax`. Empty when
+    /// highlighting is off or the language unknown.
+    pub syntax: Vec<(Range<usize>, [u8; 3])>,
+}
+
+impl LineOrigin {
+    pub fn symbol(self) -> char {
+        match self {
+            LineOrigin::Context => ' ',
+            LineOrigin::Addition => '+',
+            LineOrigin::Deletion => '-',
+            LineOrigin::NoNewline => '\\',
+        }
     }
 }
 
-pub struct MyDiffDelta<'a>(DiffDelta<'a>);
+impl Line {
+    fn new(line: DiffLine) -> Self {
+        let origin = match line.origin() {
+            '+' => LineOrigin::Addition,
+            '-' => LineOrigin::Deletion,
+            ' ' => LineOrigin::Context,
+            _ => LineOrigin::NoNewline,
+        };
+        Self {
+            origin,
+            old_lineno: line.old_lineno(),
+            new_lineno: line.new_lineno(),
+            content: String::from_utf8_lossy(line.content()).to_string(),
+            parents: vec![],
+            emphasis: vec![],
+            syntax: vec![],
+        }
+    }
 
-impl<'a> From<MyDiffDelta<'_>> for Spans<'a> {
-    fn from(diff_delta: MyDiffDelta) -> Spans<'a> {
-        let old_file = diff_delta.0.old_file();
-        let old_file = old_file.path().unwrap();
-        let old_file = old_file.to_str().unwrap();
-        let new_file = diff_delta.0.new_file();
-        let new_file = new_file.path().unwrap();
-        let new_file = new_file.to_str().unwrap();
-        Spans::from(vec![
-            Span::styled(format!("{} -> {}", old_file, new_file), Style::default().fg(Color::White)),
-            // Span::styled(format!("{}", old_file), Style::default().fg(Color::White)),
-            // Span::styled(format!("{}", new_file), Style::default().fg(Color::White)),
-        ])
+    /// The new line number, or the old one for deleted lines.
+    pub fn lineno(&self) -> Option<u32> {
+        self.new_lineno.or(self.old_lineno)
     }
 }
 
-impl MyDiffDelta<'_> {
-    pub fn id(&self) -> String {
-        let old_file = self.0.old_file();
-        let old_file = old_file.path().unwrap();
-        let new_file = self.0.new_file();
-        let new_file = new_file.path().unwrap();
-        format!("{:?} - {:?} --- {:?}", old_file, new_file, self.0.status())
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lineno = self.lineno().map(|lineno| lineno.to_string()).unwrap_or_default();
+        if self.parents.is_empty() {
+            write!(f, "{}:{}{}", lineno, self.origin.symbol(), self.content)
+        } else {
+            let symbols = self.parents.iter().map(|origin| origin.symbol()).collect::<String>();
+            write!(f, "{}:{}{}", lineno, symbols, self.content)
+        }
     }
 }
 
-impl From<MyDiffDelta<'_>> for String {
-    fn from(diff_delta: MyDiffDelta) -> String {
-        let old_file = diff_delta.0.old_file();
-        let old_file = old_file.path().unwrap();
-        let new_file = diff_delta.0.new_file();
-        let new_file = new_file.path().unwrap();
-        format!("{:?} - {:?} --- {:?}", old_file, new_file, diff_delta.0.status())
+impl Hunk {
+    /// The lines of the hunk as rows of two columns, old and new. Context is on both sides,
+    /// removed lines face the lines added in their place and the shorter side is padded with
+    /// None.
+    pub fn side_by_side(&self) -> Vec<(Option<&Line>, Option<&Line>)> {
+        let mut rows = vec![];
+        let mut i = 0;
+        while i < self.lines.len() {
+            let line = &self.lines[i];
+            match line.origin {
+                LineOrigin::Deletion | LineOrigin::Addition => {
+                    let deleted = self.lines[i..].iter().take_while(|line| line.origin == LineOrigin::Deletion).collect::<Vec<&Line>>();
+                    let added = self.lines[i + deleted.len()..].iter().take_while(|line| line.origin == LineOrigin::Addition).collect::<Vec<&Line>>();
+                    for row in 0..deleted.len().max(added.len()) {
+                        rows.push((deleted.get(row).copied(), added.get(row).copied()));
+                    }
+                    i += deleted.len() + added.len();
+                },
+                // Goes on the side of the line it's about.
+                LineOrigin::NoNewline => {
+                    match self.lines[..i].last().map(|previous| previous.origin) {
+                        Some(LineOrigin::Deletion) => rows.push((Some(line), None)),
+                        Some(LineOrigin::Addition) => rows.push((None, Some(line))),
+                        _ => rows.push((Some(line), Some(line))),
+                    }
+                    i += 1;
+                },
+                LineOrigin::Context => {
+                    rows.push((Some(line), Some(line)));
+                    i += 1;
+                },
+            }
+        }
+        rows
     }
 }
 
-pub struct MyCommit<'a>(Commit<'a>);
+impl FileDiff {
+    fn new(delta: &DiffDelta, similarity: Option<u16>, patch: Option<&Patch>, repo: &Repository) -> Result<Self, ExplorerError> {
+        let (old_file, new_file) = (delta.old_file(), delta.new_file());
+        let (old_mode, new_mode) = (old_file.mode(), new_file.mode());
+        let content = match patch {
+            _ if old_mode == FileMode::Commit || new_mode == FileMode::Commit => {
+                FileContent::Submodule { old_id: old_file.id(), new_id: new_file.id() }
+            },
+            // No patch means there is no text to show: the file is binary.
+            Some(patch) if !delta.flags().contains(DiffFlags::BINARY) => {
+                let mut hunks = vec![];
+                for hunk_i in 0..patch.num_hunks() {
+                    let (hunk, len) = patch.hunk(hunk_i)?;
+                    let mut lines = Vec::with_capacity(len);
+                    for line_i in 0..len {
+                        lines.push(Line::new(patch.line_in_hunk(hunk_i, line_i)?));
+                    }
+                    let mut hunk = Hunk {
+                        header: String::from_utf8_lossy(hunk.header()).trim_end().to_string(),
+                        lines,
+                    };
+                    word_diff::highlight(&mut hunk);
+                    hunks.push(hunk);
+                }
+                FileContent::Text(hunks)
+            },
+            _ => FileContent::Binary { old_size: blob_size(&old_file, repo), new_size: blob_size(&new_file, repo) },
+        };
+        let (insertions, deletions) = match (patch, &content) {
+            (Some(patch), FileContent::Text(_) | FileContent::Submodule { .. }) => {
+                let (_, insertions, deletions) = patch.line_stats()?;
+                (insertions, deletions)
+            },
+            _ => (0, 0),
+        };
+        Ok(Self {
+            status: delta.status(),
+            old_path: path(&old_file),
+            new_path: path(&new_file),
+            old_mode,
+            new_mode,
+            similarity,
+            content,
+            insertions,
+            deletions,
+        })
+    }
+
+    /// Letter of the status, as in `git diff --name-status`.
+    pub fn status_letter(&self) -> char {
+        match self.status {
+            Delta::Added => 'A',
+            Delta::Deleted => 'D',
+            Delta::Modified => 'M',
+            Delta::Renamed => 'R',
+            Delta::Copied => 'C',
+            Delta::Typechange => 'T',
+            Delta::Conflicted => 'U',
+            Delta::Unreadable => 'X',
+            Delta::Unmodified | Delta::Ignored | Delta::Untracked => ' ',
+        }
+    }
 
-impl<'a> From<MyCommit<'_>> for Vec<Spans<'a>> {
-    fn from(commit: MyCommit) -> Vec<Spans<'a>> {
-        let parents = commit.0.parents().map(|c| short_id(c.id())).collect::<Vec<String>>().join(" - ");
+    /// The path the file has after the change, or had before it was deleted.
+    pub fn path(&self) -> &str {
+        match self.status {
+            Delta::Deleted => &self.old_path,
+            _ => &self.new_path,
+        }
+    }
 
-        let message = &commit.0
-            .message()
-            .unwrap_or("NO COMMIT MESSAGE");
-        let committer = format!("Committer: {}", commit.0.committer().to_string());
-        let author = format!("Author: {}", commit.0.author());
-        let short_id_current_commit = short_id(commit.0.id());
-        let parents = format!("PARENTS: {}", parents);
-        vec![
-            Spans::from(vec![Span::styled(message.to_string(), Style::default().fg(Color::White))]), // TODO: message will not generate spans with new lines // TODO: Can this be replicated for ListItems? To add new lines there with multiple spans?
-            Spans::from(vec![Span::styled(committer, Style::default().fg(Color::Red))]),
-            Spans::from(vec![Span::styled(author, Style::default().fg(Color::White))]),
-            Spans::from(vec![Span::styled(short_id_current_commit, Style::default().fg(Color::White))]),
-            Spans::from(vec![Span::styled(parents, Style::default().fg(Color::White))]),
-        ]
+    pub fn hunks(&self) -> &[Hunk] {
+        match &self.content {
+            FileContent::Text(hunks) => hunks,
+            _ => &[],
+        }
+    }
+
+    fn hunks_mut(&mut self) -> &mut [Hunk] {
+        match &mut self.content {
+            FileContent::Text(hunks) => hunks,
+            _ => &mut [],
+        }
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.hunks().iter().flat_map(|hunk| hunk.lines.iter())
     }
 }
 
-// type MyDiffHunk<'a> = DiffHunk<'a>;
-pub struct MyDiffHunk<'a>(DiffHunk<'a>);
+/// One line describing the file: what happened to it and, when that's all there is to show,
+/// its mode change, binary size change or submodule update.
+impl fmt::Display for FileDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let similarity = self.similarity.map(|similarity| format!(" ({}% similar)", similarity)).unwrap_or_default();
+        match self.status {
+            Delta::Added => write!(f, "added {}", self.new_path)?,
+            Delta::Deleted => write!(f, "deleted {}", self.old_path)?,
+            Delta::Renamed => write!(f, "renamed {} -> {}{}", self.old_path, self.new_path, similarity)?,
+            Delta::Copied => write!(f, "copied {} -> {}{}", self.old_path, self.new_path, similarity)?,
+            _ => write!(f, "{}", self.new_path)?,
+        }
 
-impl From<MyDiffHunk<'_>> for String {
-    fn from(hunk: MyDiffHunk) -> String {
-        String::from_utf8(hunk.0.header().to_vec()).unwrap()
+        let added_or_deleted = matches!(self.status, Delta::Added | Delta::Deleted);
+        if !added_or_deleted && self.old_mode != self.new_mode {
+            write!(f, ", mode {:o} -> {:o}", i32::from(self.old_mode), i32::from(self.new_mode))?;
+        }
+        match self.content {
+            FileContent::Submodule { old_id, new_id } => write!(f, ", submodule {}..{}", short_id(old_id), short_id(new_id)),
+            FileContent::Binary { old_size, new_size } => {
+                write!(f, ", binary {} -> {} bytes ({:+})", old_size, new_size, new_size as i64 - old_size as i64)
+            },
+            FileContent::Text(_) => Ok(()),
+        }
     }
 }
 
-impl ParsedDiff<'_> {
-    pub fn new(commit_1: Commit, commit_2: Option<Oid>, repo: &Repository) -> Self {
-        let commit_1_oid = commit_1.id();
-        let commit_2_oid = commit_2;
-        let current_commit = commit_1;
-        let test_lines;
+// Added and deleted files have no path on one side.
+fn path(file: &DiffFile) -> String {
+    match file.path() {
+        Some(path) => path.to_string_lossy().to_string(),
+        None => String::from("/dev/null"),
+    }
+}
 
-        let my_current_commit: MyCommit = MyCommit(current_commit.clone());
-        let mut diff_spans: Vec<Spans> = my_current_commit.into();
+// Read from the object header, the blob itself is never loaded.
+fn blob_size(file: &DiffFile, repo: &Repository) -> u64 {
+    if file.id().is_zero() {
+        return 0
+    }
+    match repo.odb().and_then(|odb| odb.read_header(file.id())) {
+        Ok((size, _)) => size as u64,
+        Err(_) => file.size(),
+    }
+}
 
-        let mut line_spans_buffer: Vec<Spans> = vec![];
+impl CommitDetails {
+    fn new(commit: &Commit) -> Self {
+        Self {
+            id: commit.id(),
+            parents: commit.parent_ids().collect(),
+            message: commit.message().unwrap_or("NO COMMIT MESSAGE").to_string(),
+            author: commit.author().to_string(),
+            committer: commit.committer().to_string(),
+        }
+    }
+}
+
+impl ParsedDiff {
+    /// Similarity of every renamed or copied file, by `similarity_key`. git2 doesn't expose it on
+    /// the delta, the raw format prints it next to the status (`R085`).
+    fn similarities(diff: &Diff) -> Result<HashMap<String, u16>, ExplorerError> {
+        let mut similarities = HashMap::new();
+        diff.print(DiffFormat::Raw, |_, _, line| {
+            let content = String::from_utf8_lossy(line.content());
+            // `:100644 100644 <old id> <new id> R085<TAB>old path new path`
+            if let Some((header, paths)) = content.trim_end_matches('\n').split_once('\t') {
+                let status = header.split_whitespace().last();
+                let score = status.and_then(|status| status.get(1..)).and_then(|score| score.parse().ok());
+                if let Some(score) = score {
+                    similarities.insert(paths.to_string(), score);
+                }
+            }
+            true
+        })?;
+        Ok(similarities)
+    }
+
+    // libgit2 separates the two paths of a rename with a space in the raw format.
+    fn similarity_key(delta: &DiffDelta) -> String {
+        format!("{} {}", path(&delta.old_file()), path(&delta.new_file()))
+    }
 
+    /// `commit_1` against `commit_2`, or just the commit details without `commit_2`.
+    pub fn new(commit_1: Commit, commit_2: Option<Oid>, repo: &Repository) -> Result<Self, ExplorerError> {
         match commit_2 {
             Some(oid) => {
-                let sub_tree_oid_previous = oid;
-                let previous_commit = repo.find_commit(sub_tree_oid_previous).unwrap();
-
-                let my_first_diff = repo.diff_tree_to_tree(
-                    previous_commit.tree().ok().as_ref(),
-                    current_commit.tree().ok().as_ref(),
-                    None
-                ).unwrap();
-
-                let mut diff_delta_previous = String::new();
-
-                let _foreach_result = my_first_diff.foreach(
-                    &mut |diff_delta, _| {
-                        let delta = MyDiffDelta(diff_delta);
-                        // let delta: String = delta.into();
-                        // trace!("{}", delta);
-                        let d_spans: Spans = delta.into();
-                        diff_spans.push(d_spans);
-                        true
-                    },
-                    None,
-                    Some(&mut |_, _hunk| {
-                        true
-                    }),
-                    Some(&mut |diff_delta, hunk, line| {
-                        let diff_delta = MyDiffDelta(diff_delta);
-
-                        let diff_delta_current = diff_delta.id();
-                        if diff_delta_current != diff_delta_previous {
-                            line_spans_buffer.push(diff_delta.into());
-                            diff_delta_previous = diff_delta_current;
-                        }
-                        // let diff_delta_str: String = diff_delta.into();
-
-                        // info!("{}", diff_delta_str);
-                        match hunk {
-                            Some(diff_hunk) => {
-                                let hunk: MyDiffHunk = MyDiffHunk(diff_hunk);
-                                let hunk: String = hunk.into();
-                                let line = MyDiffLine(line);
-                                let style = Style::default().fg(Color::White);
-                                let spans: Spans = line.into();
-                                line_spans_buffer.push(spans);
-                            }
-                            None => {
-                                error!("NO DIFF HUNK for {:?}", hunk);
-                            }
-                        }
-                        true
-                    }),
-                );
+                let previous_commit = find_commit(repo, oid)?;
+                Self::between_trees(&commit_1, Some(&previous_commit.tree()?), commit_2, repo)
             },
-            None => {}
+            None => Ok(Self {
+                commit: CommitDetails::new(&commit_1),
+                compared: None,
+                files: vec![],
+                insertions: 0,
+                deletions: 0,
+            }),
         }
-        // let t = Text::from(diff_spans);
-        test_lines = [diff_spans, vec![Spans::from(vec![])], line_spans_buffer].concat();
-        Self {
-            commit_1_oid,
-            commit_2_oid,
-            test_lines,
+    }
+
+    /// `commit` against its nth parent, zero based, or against the empty tree for a root commit.
+    pub fn against_parent(commit: Commit, parent: usize, repo: &Repository) -> Result<Self, ExplorerError> {
+        if commit.parent_count() == 0 {
+            return Self::between_trees(&commit, None, None, repo)
         }
+        let parent = commit.parent(parent)?;
+        Self::between_trees(&commit, Some(&parent.tree()?), Some(parent.id()), repo)
+    }
+
+    fn between_trees(commit: &Commit, old_tree: Option<&Tree>, compared: Option<Oid>, repo: &Repository) -> Result<Self, ExplorerError> {
+        let mut diff = repo.diff_tree_to_tree(old_tree, Some(&commit.tree()?), None)?;
+        diff.find_similar(Some(DiffFindOptions::new().renames(true).copies(true)))?;
+        let similarities = Self::similarities(&diff)?;
+
+        let mut files = vec![];
+        for i in 0..diff.deltas().len() {
+            let patch = Patch::from_diff(&diff, i)?;
+            let delta = match diff.get_delta(i) {
+                Some(delta) => delta,
+                None => continue,
+            };
+            let similarity = similarities.get(&Self::similarity_key(&delta)).copied();
+            files.push(FileDiff::new(&delta, similarity, patch.as_ref(), repo)?);
+        }
+
+        let stats = diff.stats()?;
+        Ok(Self {
+            commit: CommitDetails::new(commit),
+            compared,
+            files,
+            insertions: stats.insertions(),
+            deletions: stats.deletions(),
+        })
+    }
+
+    /// The diff as plain text, each file's header followed by its lines, to compare in tests.
+    #[cfg(test)]
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.files.iter().flat_map(|file| {
+            std::iter::once(file.to_string()).chain(file.lines().map(|line| line.to_string()))
+        })
     }
 }
 
+mod combined;
+mod word_diff;
+mod stat;
+pub mod syntax;
 
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,264 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use git2::{Commit, Delta, DiffOptions, FileMode, Oid, Patch, Repository, Tree};
+
+use crate::explorer::error::ExplorerError;
+use super::{CommitDetails, FileContent, FileDiff, Hunk, Line, LineOrigin, ParsedDiff};
+
+/// Unchanged lines kept around the changes of a combined hunk.
+const CONTEXT: usize = 3;
+
+/// A file as it is in one tree. Absent from the tree when None.
+type Version = Option<(Oid, FileMode)>;
+
+impl ParsedDiff {
+    /// A merge against all of its parents at once, like `git show --cc`. Only files that differ
+    /// from every parent are listed, and in them only the hunks that differ from every parent:
+    /// what was taken as is from one side of the merge isn't shown.
+    pub fn combined(commit: Commit, repo: &Repository) -> Result<Self, ExplorerError> {
+        let tree = commit.tree()?;
+        let parent_trees = commit.parents().map(|parent| parent.tree()).collect::<Result<Vec<Tree>, _>>()?;
+
+        let mut files = vec![];
+        for path in changed_from_all(&parent_trees, &tree, repo)? {
+            let parents = parent_trees.iter().map(|parent_tree| version(parent_tree, &path)).collect::<Vec<Version>>();
+            let file = combined_file(&path, &parents, version(&tree, &path), repo)?;
+            // Nothing is left of a text file whose changes were each taken as is from one parent.
+            if !file.hunks().is_empty() || !matches!(file.content, FileContent::Text(_)) {
+                files.push(file);
+            }
+        }
+
+        let insertions = files.iter().map(|file| file.insertions).sum();
+        let deletions = files.iter().map(|file| file.deletions).sum();
+        Ok(Self {
+            commit: CommitDetails::new(&commit),
+            compared: None,
+            files,
+            insertions,
+            deletions,
+        })
+    }
+}
+
+/// Paths that differ between `tree` and each of `parent_trees`.
+fn changed_from_all(parent_trees: &[Tree], tree: &Tree, repo: &Repository) -> Result<Vec<String>, ExplorerError> {
+    let mut common: Option<BTreeSet<String>> = None;
+    for parent_tree in parent_trees {
+        let diff = repo.diff_tree_to_tree(Some(parent_tree), Some(tree), None)?;
+        let paths = diff.deltas()
+            .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()).map(|path| path.to_string_lossy().to_string()))
+            .collect::<BTreeSet<String>>();
+        common = Some(match common {
+            Some(common) => common.intersection(&paths).cloned().collect(),
+            None => paths,
+        });
+    }
+    Ok(common.unwrap_or_default().into_iter().collect())
+}
+
+fn version(tree: &Tree, path: &str) -> Version {
+    let entry = tree.get_path(Path::new(path)).ok()?;
+    let mode = match entry.filemode() {
+        0o100644 => FileMode::Blob,
+        0o100755 => FileMode::BlobExecutable,
+        0o120000 => FileMode::Link,
+        0o160000 => FileMode::Commit,
+        0o040000 => FileMode::Tree,
+        _ => FileMode::Unreadable,
+    };
+    Some((entry.id(), mode))
+}
+
+fn combined_file(path: &str, parents: &[Version], result: Version, repo: &Repository) -> Result<FileDiff, ExplorerError> {
+    let status = match result {
+        None => Delta::Deleted,
+        Some(_) if parents.iter().all(Option::is_none) => Delta::Added,
+        Some(_) => Delta::Modified,
+    };
+    // The header describes the change from the first parent.
+    let old_path = if parents[0].is_some() { path.to_string() } else { String::from("/dev/null") };
+    let new_path = if result.is_some() { path.to_string() } else { String::from("/dev/null") };
+    let mode = |version: &Version| version.map(|(_, mode)| mode).unwrap_or(FileMode::Unreadable);
+    let id = |version: &Version| version.map(|(id, _)| id).unwrap_or_else(Oid::zero);
+
+    let content = if parents.iter().chain(Some(&result)).any(|version| mode(version) == FileMode::Commit) {
+        FileContent::Submodule { old_id: id(&parents[0]), new_id: id(&result) }
+    } else {
+        let blob = |version: &Version| -> Result<Vec<u8>, ExplorerError> {
+            match version {
+                Some((id, _)) => Ok(repo.find_blob(*id)?.content().to_vec()),
+                None => Ok(vec![]),
+            }
+        };
+        let parent_contents = parents.iter().map(blob).collect::<Result<Vec<Vec<u8>>, _>>()?;
+        let result_content = blob(&result)?;
+        let is_binary = |content: &Vec<u8>| content.contains(&0);
+        if parent_contents.iter().chain(Some(&result_content)).any(is_binary) {
+            FileContent::Binary { old_size: parent_contents[0].len() as u64, new_size: result_content.len() as u64 }
+        } else {
+            FileContent::Text(combined_hunks(&parent_contents, &result_content)?)
+        }
+    };
+
+    // Counted on the shown hunks, a line added or dropped against any parent counts once.
+    let count = |origin| content_lines(&content).filter(|line| line.origin == origin).count();
+    let (insertions, deletions) = (count(LineOrigin::Addition), count(LineOrigin::Deletion));
+    Ok(FileDiff {
+        status,
+        old_path,
+        new_path,
+        old_mode: mode(&parents[0]),
+        new_mode: mode(&result),
+        similarity: None,
+        content,
+        insertions,
+        deletions,
+    })
+}
+
+fn content_lines(content: &FileContent) -> impl Iterator<Item = &Line> {
+    let hunks = match content {
+        FileContent::Text(hunks) => &hunks[..],
+        _ => &[],
+    };
+    hunks.iter().flat_map(|hunk| hunk.lines.iter())
+}
+
+/// A line of the result, or a line of one or more parents that the result dropped.
+struct Row {
+    content: String,
+    columns: Vec<LineOrigin>,
+    result_lineno: Option<u32>,
+    /// Line numbers in the result and in each parent before this row.
+    before: (u32, Vec<u32>),
+}
+
+impl Row {
+    fn is_change(&self) -> bool {
+        self.columns.iter().any(|origin| *origin != LineOrigin::Context)
+    }
+
+    // Result lines are in every parent that didn't add them, dropped lines in those they are
+    // dropped from.
+    fn in_parent(&self, parent: usize) -> bool {
+        match self.result_lineno {
+            Some(_) => self.columns[parent] == LineOrigin::Context,
+            None => self.columns[parent] == LineOrigin::Deletion,
+        }
+    }
+}
+
+fn combined_hunks(parents: &[Vec<u8>], result: &[u8]) -> Result<Vec<Hunk>, ExplorerError> {
+    let result_lines = result.split_inclusive(|byte| *byte == b'\n').collect::<Vec<&[u8]>>();
+    // Which parents each result line was added to, and the lines each parent had before it.
+    let mut added = vec![vec![false; parents.len()]; result_lines.len()];
+    let mut dropped: Vec<Vec<(Vec<u8>, Vec<bool>)>> = vec![vec![]; result_lines.len() + 1];
+
+    for (parent_i, parent) in parents.iter().enumerate() {
+        let mut options = DiffOptions::new();
+        options.context_lines(0);
+        let patch = Patch::from_buffers(parent, None, result, None, Some(&mut options))?;
+        for hunk_i in 0..patch.num_hunks() {
+            let (hunk, len) = patch.hunk(hunk_i)?;
+            // A hunk that only drops lines starts after the result line it follows.
+            let position = if hunk.new_lines() == 0 { hunk.new_start() } else { hunk.new_start() - 1 } as usize;
+            // The same line dropped from several parents is shown once.
+            let mut cursor = 0;
+            for line_i in 0..len {
+                let line = patch.line_in_hunk(hunk_i, line_i)?;
+                match (line.origin(), line.new_lineno()) {
+                    ('+', Some(lineno)) => added[lineno as usize - 1][parent_i] = true,
+                    ('-', _) => {
+                        let lines = &mut dropped[position];
+                        match lines.iter().skip(cursor).position(|(content, from)| content == line.content() && !from[parent_i]) {
+                            Some(i) => {
+                                lines[cursor + i].1[parent_i] = true;
+                                cursor += i + 1;
+                            },
+                            None => {
+                                let mut from = vec![false; parents.len()];
+                                from[parent_i] = true;
+                                lines.push((line.content().to_vec(), from));
+                                cursor = lines.len();
+                            },
+                        }
+                    },
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    let mut rows = vec![];
+    let (mut result_lineno, mut parent_linenos) = (1, vec![1; parents.len()]);
+    for (k, lines) in dropped.into_iter().enumerate() {
+        for (content, from) in lines {
+            let columns = from.iter().map(|from| if *from { LineOrigin::Deletion } else { LineOrigin::Context }).collect();
+            let row = Row { content: String::from_utf8_lossy(&content).to_string(), columns, result_lineno: None, before: (result_lineno, parent_linenos.clone()) };
+            for (parent_i, lineno) in parent_linenos.iter_mut().enumerate() {
+                if row.in_parent(parent_i) { *lineno += 1 }
+            }
+            rows.push(row);
+        }
+        if let Some(line) = result_lines.get(k) {
+            let columns = added[k].iter().map(|added| if *added { LineOrigin::Addition } else { LineOrigin::Context }).collect();
+            let row = Row { content: String::from_utf8_lossy(line).to_string(), columns, result_lineno: Some(result_lineno), before: (result_lineno, parent_linenos.clone()) };
+            for (parent_i, lineno) in parent_linenos.iter_mut().enumerate() {
+                if row.in_parent(parent_i) { *lineno += 1 }
+            }
+            result_lineno += 1;
+            rows.push(row);
+        }
+    }
+
+    // Changes closer than twice the context share a hunk.
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for (i, _) in rows.iter().enumerate().filter(|(_, row)| row.is_change()) {
+        let (start, end) = (i.saturating_sub(CONTEXT), (i + CONTEXT + 1).min(rows.len()));
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    let hunks = ranges.into_iter()
+        // Where the result is one parent's version, that side of the merge was taken as is.
+        .filter(|(start, end)| (0..parents.len()).all(|parent_i| {
+            rows[*start..*end].iter().any(|row| row.columns[parent_i] != LineOrigin::Context)
+        }))
+        .map(|(start, end)| combined_hunk(&rows[start..end], parents.len()))
+        .collect();
+    Ok(hunks)
+}
+
+fn combined_hunk(rows: &[Row], parent_count: usize) -> Hunk {
+    // Like unified hunk headers, an empty range starts at the line before it.
+    let range = |start: u32, count: u32| format!("{},{}", if count == 0 { start - 1 } else { start }, count);
+    let parent_counts = (0..parent_count)
+        .map(|parent_i| rows.iter().filter(|row| row.in_parent(parent_i)).count() as u32)
+        .collect::<Vec<u32>>();
+    let result_count = rows.iter().filter(|row| row.result_lineno.is_some()).count() as u32;
+    let (result_start, parent_starts) = &rows[0].before;
+
+    let marker = "@".repeat(parent_count + 1);
+    let parent_ranges = parent_starts.iter().zip(parent_counts.iter())
+        .map(|(start, count)| format!("-{}", range(*start, *count)))
+        .collect::<Vec<String>>()
+        .join(" ");
+    let header = format!("{} {} +{} {}", marker, parent_ranges, range(*result_start, result_count), marker);
+
+    let lines = rows.iter().map(|row| {
+        let origin = match row.result_lineno {
+            None => LineOrigin::Deletion,
+            Some(_) if row.is_change() => LineOrigin::Addition,
+            Some(_) => LineOrigin::Context,
+        };
+        // Numbered like the first parent, for lines it has.
+        let old_lineno = if row.in_parent(0) { Some(row.before.1[0]) } else { None };
+        Line { origin, old_lineno, new_lineno: row.result_lineno, content: row.content.clone(), parents: row.columns.clone(), emphasis: vec![], syntax: vec![] }
+    }).collect();
+
+    Hunk { header, lines }
+}
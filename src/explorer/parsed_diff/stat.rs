@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use git2::Delta;
+
+use super::{FileContent, FileDiff, ParsedDiff};
+
+/// Directories with a smaller share of the changes are left out of the dirstat, like git does
+/// by default.
+const DIRSTAT_THRESHOLD_PERCENT: f64 = 3.0;
+
+/// One file of a `git diff --stat` block.
+pub struct StatLine {
+    pub name: String,
+    /// Number of changed lines, or the size change of a binary file.
+    pub changes: String,
+    /// Length of the `+` and `-` bars.
+    pub plus: usize,
+    pub minus: usize,
+}
+
+impl FileDiff {
+    fn stat_name(&self) -> String {
+        match self.status {
+            Delta::Renamed | Delta::Copied => format!("{} => {}", self.old_path, self.new_path),
+            _ => self.path().to_string(),
+        }
+    }
+}
+
+// Bars are scaled down to `width` when the biggest change doesn't fit, a change always keeps at
+// least one character.
+fn bars(insertions: usize, deletions: usize, most: usize, width: usize) -> (usize, usize) {
+    if most <= width {
+        return (insertions, deletions)
+    }
+    let scale = |count: usize| if count == 0 { 0 } else { ((count * width) as f64 / most as f64).round().max(1.0) as usize };
+    let total = scale(insertions + deletions);
+    let plus = scale(insertions).min(total);
+    let minus = if deletions == 0 { 0 } else { (total - plus).max(1) };
+    (plus, minus)
+}
+
+impl ParsedDiff {
+    /// A line per file, with bars no longer than `bar_width`.
+    pub fn stat(&self, bar_width: usize) -> Vec<StatLine> {
+        let most = self.files.iter().map(|file| file.insertions + file.deletions).max().unwrap_or(0);
+        self.files.iter().map(|file| {
+            let (plus, minus) = bars(file.insertions, file.deletions, most, bar_width);
+            let changes = match file.content {
+                FileContent::Binary { old_size, new_size } => format!("Bin {} -> {} bytes", old_size, new_size),
+                _ => (file.insertions + file.deletions).to_string(),
+            };
+            StatLine { name: file.stat_name(), changes, plus, minus }
+        }).collect()
+    }
+
+    /// `3 files changed, 10 insertions(+), 2 deletions(-)`
+    pub fn stat_summary(&self) -> String {
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        let mut summary = format!("{} file{} changed", self.files.len(), plural(self.files.len()));
+        if self.insertions > 0 {
+            summary.push_str(&format!(", {} insertion{}(+)", self.insertions, plural(self.insertions)));
+        }
+        if self.deletions > 0 {
+            summary.push_str(&format!(", {} deletion{}(-)", self.deletions, plural(self.deletions)));
+        }
+        summary
+    }
+
+    /// Share of the changed lines in each directory, in percent, like `git diff --dirstat`.
+    /// Files at the top of the repository are under `./`.
+    pub fn dirstat(&self) -> Vec<(f64, String)> {
+        let mut directories: BTreeMap<String, usize> = BTreeMap::new();
+        for file in self.files.iter() {
+            let directory = match Path::new(file.path()).parent().and_then(|parent| parent.to_str()) {
+                Some(parent) if !parent.is_empty() => format!("{}/", parent),
+                _ => String::from("./"),
+            };
+            *directories.entry(directory).or_default() += file.insertions + file.deletions;
+        }
+        let total = directories.values().sum::<usize>();
+        if total == 0 {
+            return vec![]
+        }
+        directories.into_iter()
+            .map(|(directory, changes)| (changes as f64 * 100.0 / total as f64, directory))
+            .filter(|(percent, _)| *percent >= DIRSTAT_THRESHOLD_PERCENT)
+            .collect()
+    }
+}
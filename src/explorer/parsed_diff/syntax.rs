@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+use super::{FileDiff, LineOrigin, ParsedDiff};
+
+/// Lines longer than this are left plain, the grammars get slow on minified files.
+const MAX_LINE_LENGTH: usize = 2000;
+
+const THEME: &str = "base16-eighties.dark";
+
+/// Grammars and theme bundled with syntect. Loading them takes a while, keep one around.
+pub struct SyntaxHighlighter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+}
+
+impl SyntaxHighlighter {
+    pub fn new() -> Self {
+        let mut themes = ThemeSet::load_defaults();
+        Self {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            theme: themes.themes.remove(THEME).unwrap_or_default(),
+        }
+    }
+
+    /// By file name first (`Makefile`), then extension, then first line (`#!/bin/sh`).
+    fn syntax(&self, file: &FileDiff) -> Option<&SyntaxReference> {
+        let path = Path::new(file.path());
+        let name = path.file_name().and_then(|name| name.to_str());
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        let first_line = file.lines().find(|line| line.new_lineno == Some(1) || line.old_lineno == Some(1));
+        name.and_then(|name| self.syntaxes.find_syntax_by_extension(name))
+            .or_else(|| extension.and_then(|extension| self.syntaxes.find_syntax_by_extension(extension)))
+            .or_else(|| first_line.and_then(|line| self.syntaxes.find_syntax_by_first_line(&line.content)))
+            .filter(|syntax| syntax.name != "Plain Text")
+    }
+
+    fn highlight_file(&self, file: &mut FileDiff) {
+        let syntax = match self.syntax(file) {
+            Some(syntax) => syntax,
+            None => return,
+        };
+        for hunk in file.hunks_mut() {
+            // Both sides of the hunk are parsed on their own, a removed line doesn't open a string
+            // or a comment on the added ones. The file above the hunk is unknown, each starts fresh.
+            let mut old = HighlightLines::new(syntax, &self.theme);
+            let mut new = HighlightLines::new(syntax, &self.theme);
+            for line in hunk.lines.iter_mut() {
+                if line.content.len() > MAX_LINE_LENGTH {
+                    continue
+                }
+                let side = match line.origin {
+                    LineOrigin::Deletion => &mut old,
+                    LineOrigin::Addition => &mut new,
+                    LineOrigin::Context => {
+                        // Context is on both sides, keep the old one in step.
+                        let _ = old.highlight_line(&line.content, &self.syntaxes);
+                        &mut new
+                    },
+                    LineOrigin::NoNewline => continue,
+                };
+                let regions = match side.highlight_line(&line.content, &self.syntaxes) {
+                    Ok(regions) => regions,
+                    Err(_) => continue,
+                };
+                let mut start = 0;
+                line.syntax = regions.into_iter().map(|(style, text)| {
+                    let range = start..start + text.len();
+                    start = range.end;
+                    (range, [style.foreground.r, style.foreground.g, style.foreground.b])
+                }).collect();
+            }
+        }
+    }
+}
+
+impl ParsedDiff {
+    /// Colors the content of every line after the language of its file.
+    pub fn highlight_syntax(&mut self, highlighter: &SyntaxHighlighter) {
+        for file in self.files.iter_mut() {
+            highlighter.highlight_file(file);
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use git2::{FileMode, Oid};
+
+use crate::explorer::parsed_diff::{Line, LineOrigin, ParsedDiff};
+use crate::explorer::parsed_diff::syntax::SyntaxHighlighter;
+use crate::explorer::test_repo::TestRepo;
+
+/// The diff of `new` against `old` as plain text.
+fn diff(test_repo: &TestRepo, new: Oid, old: Oid) -> Vec<String> {
+    let commit = test_repo.repo.find_commit(new).unwrap();
+    let parsed_diff = ParsedDiff::new(commit, Some(old), &test_repo.repo).unwrap();
+    parsed_diff.lines().collect()
+}
+
+const TEXT: &[u8] = b"one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\n";
+
+#[test]
+fn added_modified_and_deleted_files() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit_files("A", &[], &[("kept", b"a\n", FileMode::Blob), ("gone", b"x\n", FileMode::Blob)], 100);
+    let b = test_repo.commit_files("B", &[a], &[("kept", b"b\n", FileMode::Blob), ("new", b"y\n", FileMode::Blob)], 200);
+
+    assert_eq!(diff(&test_repo, b, a), [
+        "deleted gone",
+        "1:-x\n",
+        "kept",
+        "1:-a\n",
+        "1:+b\n",
+        "added new",
+        "1:+y\n",
+    ][..]);
+
+    let commit = test_repo.repo.find_commit(b).unwrap();
+    let parsed_diff = ParsedDiff::new(commit, Some(a), &test_repo.repo).unwrap();
+    let letters = parsed_diff.files.iter().map(|file| file.status_letter()).collect::<String>();
+    assert_eq!(letters, "DMA");
+}
+
+#[test]
+fn renames_show_their_similarity() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit_files("A", &[], &[("before", TEXT, FileMode::Blob)], 100);
+    let b = test_repo.commit_files("B", &[a], &[("after", b"one\ntwo\nthree\nfour\nfive\nsix\nseven\nEIGHT\n", FileMode::Blob)], 200);
+
+    let lines = diff(&test_repo, b, a);
+    assert_eq!(lines[0], "renamed before -> after (87% similar)");
+    assert_eq!(&lines[1..], ["5: five\n", "6: six\n", "7: seven\n", "8:-eight\n", "8:+EIGHT\n"]);
+}
+
+#[test]
+fn pure_renames_are_not_binary() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit_files("A", &[], &[("before", TEXT, FileMode::Blob)], 100);
+    let b = test_repo.commit_files("B", &[a], &[("after", TEXT, FileMode::Blob)], 200);
+
+    assert_eq!(diff(&test_repo, b, a), ["renamed before -> after (100% similar)"]);
+}
+
+#[test]
+fn binary_files_show_their_size_change() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit_files("A", &[], &[("image", b"\x00\x01\x02", FileMode::Blob)], 100);
+    let b = test_repo.commit_files("B", &[a], &[("image", b"\x00\x01\x02\x03\x04", FileMode::Blob)], 200);
+
+    assert_eq!(diff(&test_repo, b, a), ["image, binary 3 -> 5 bytes (+2)"]);
+}
+
+#[test]
+fn mode_changes_and_submodule_updates() {
+    let test_repo = TestRepo::new();
+    let old_commit = "1111111111111111111111111111111111111111".as_bytes();
+    let new_commit = "2222222222222222222222222222222222222222".as_bytes();
+    let a = test_repo.commit_files("A", &[], &[("script", b"run\n", FileMode::Blob), ("vendor", old_commit, FileMode::Commit)], 100);
+    let b = test_repo.commit_files("B", &[a], &[("script", b"run\n", FileMode::BlobExecutable), ("vendor", new_commit, FileMode::Commit)], 200);
+
+    assert_eq!(diff(&test_repo, b, a), [
+        "script, mode 100644 -> 100755",
+        "vendor, submodule 1111111..2222222",
+    ][..]);
+}
+
+#[test]
+fn text_that_is_not_utf8_is_decoded_lossily() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit_files("A", &[], &[("latin1", b"caf\xe9\n", FileMode::Blob)], 100);
+    let b = test_repo.commit_files("B", &[a], &[("latin1", b"caf\xe9s\n", FileMode::Blob)], 200);
+
+    assert_eq!(diff(&test_repo, b, a), ["latin1", "1:-caf\u{fffd}\n", "1:+caf\u{fffd}s\n"]);
+}
+
+#[test]
+fn files_are_split_into_hunks_with_both_line_numbers() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit_files("A", &[], &[("text", TEXT, FileMode::Blob)], 100);
+    let b = test_repo.commit_files("B", &[a], &[("text", b"zero\none\ntwo\nthree\nfour\nfive\nsix\nseven\n", FileMode::Blob)], 200);
+
+    let commit = test_repo.repo.find_commit(b).unwrap();
+    let parsed_diff = ParsedDiff::new(commit, Some(a), &test_repo.repo).unwrap();
+    assert_eq!(parsed_diff.commit.parents, [a]);
+    assert_eq!(parsed_diff.files.len(), 1);
+
+    let hunks = parsed_diff.files[0].hunks();
+    assert_eq!(hunks.len(), 2);
+    assert_eq!(hunks[0].header, "@@ -1,3 +1,4 @@");
+    let added = &hunks[0].lines[0];
+    assert_eq!((added.origin, added.old_lineno, added.new_lineno), (LineOrigin::Addition, None, Some(1)));
+    let context = &hunks[0].lines[1];
+    assert_eq!((context.origin, context.old_lineno, context.new_lineno), (LineOrigin::Context, Some(1), Some(2)));
+    let deleted = hunks[1].lines.last().unwrap();
+    assert_eq!((deleted.origin, deleted.old_lineno, deleted.new_lineno, deleted.content.as_str()), (LineOrigin::Deletion, Some(8), None, "eight\n"));
+}
+
+/// Twenty numbered lines, with `changes` replacing some of them.
+fn numbered(changes: &[(usize, &str)]) -> Vec<u8> {
+    (1..=20)
+        .map(|i| match changes.iter().find(|(line, _)| *line == i) {
+            Some((_, text)) => format!("{}\n", text),
+            None => format!("line {}\n", i),
+        })
+        .collect::<String>()
+        .into_bytes()
+}
+
+#[test]
+fn root_commits_are_diffed_against_the_empty_tree() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit_files("A", &[], &[("first", b"hello\n", FileMode::Blob)], 100);
+
+    let commit = test_repo.repo.find_commit(a).unwrap();
+    let parsed_diff = ParsedDiff::against_parent(commit, 0, &test_repo.repo).unwrap();
+    assert_eq!(parsed_diff.compared, None);
+    assert_eq!(parsed_diff.lines().collect::<Vec<String>>(), ["added first", "1:+hello\n"]);
+}
+
+#[test]
+fn merges_are_diffed_against_the_chosen_parent() {
+    let test_repo = TestRepo::new();
+    let base = test_repo.commit_files("base", &[], &[("f", b"base\n", FileMode::Blob)], 100);
+    let ours = test_repo.commit_files("ours", &[base], &[("f", b"ours\n", FileMode::Blob)], 200);
+    let theirs = test_repo.commit_files("theirs", &[base], &[("f", b"theirs\n", FileMode::Blob)], 300);
+    let merge = test_repo.commit_files("merge", &[ours, theirs], &[("f", b"ours\n", FileMode::Blob)], 400);
+
+    let against = |parent| {
+        let commit = test_repo.repo.find_commit(merge).unwrap();
+        ParsedDiff::against_parent(commit, parent, &test_repo.repo).unwrap()
+    };
+    assert!(against(0).files.is_empty());
+    assert_eq!(against(1).compared, Some(theirs));
+    assert_eq!(against(1).lines().collect::<Vec<String>>(), ["f", "1:-theirs\n", "1:+ours\n"]);
+}
+
+#[test]
+fn combined_diffs_only_show_what_differs_from_every_parent() {
+    let test_repo = TestRepo::new();
+    let text = numbered(&[]);
+    let base = test_repo.commit_files("base", &[], &[("f", &text, FileMode::Blob), ("g", &text, FileMode::Blob), ("h", &text, FileMode::Blob)], 100);
+    let ours = test_repo.commit_files("ours", &[base], &[
+        ("f", &numbered(&[(2, "ours")]), FileMode::Blob),
+        ("g", &numbered(&[(1, "ours")]), FileMode::Blob),
+        ("h", &text, FileMode::Blob),
+    ], 200);
+    let theirs = test_repo.commit_files("theirs", &[base], &[
+        ("f", &numbered(&[(18, "theirs")]), FileMode::Blob),
+        ("g", &numbered(&[(20, "theirs")]), FileMode::Blob),
+        ("h", &numbered(&[(5, "theirs")]), FileMode::Blob),
+    ], 300);
+    // `f` gets a change of its own, `g` and `h` are merged cleanly.
+    let merge = test_repo.commit_files("merge", &[ours, theirs], &[
+        ("f", &numbered(&[(2, "ours"), (10, "merge"), (18, "theirs")]), FileMode::Blob),
+        ("g", &numbered(&[(1, "ours"), (20, "theirs")]), FileMode::Blob),
+        ("h", &numbered(&[(5, "theirs")]), FileMode::Blob),
+    ], 400);
+
+    let commit = test_repo.repo.find_commit(merge).unwrap();
+    let parsed_diff = ParsedDiff::combined(commit, &test_repo.repo).unwrap();
+    assert_eq!(parsed_diff.files.len(), 1);
+    assert_eq!(parsed_diff.files[0].hunks()[0].header, "@@@ -7,7 -7,7 +7,7 @@@");
+    assert_eq!(parsed_diff.lines().collect::<Vec<String>>(), [
+        "f",
+        "7:  line 7\n",
+        "8:  line 8\n",
+        "9:  line 9\n",
+        "10:--line 10\n",
+        "10:++merge\n",
+        "11:  line 11\n",
+        "12:  line 12\n",
+        "13:  line 13\n",
+    ][..]);
+}
+
+/// The emphasized parts of every line when a file goes from `old` to `new`.
+fn emphasis(test_repo: &TestRepo, old: &[u8], new: &[u8]) -> Vec<Vec<String>> {
+    let a = test_repo.commit_files("A", &[], &[("f", old, FileMode::Blob)], 100);
+    let b = test_repo.commit_files("B", &[a], &[("f", new, FileMode::Blob)], 200);
+    let commit = test_repo.repo.find_commit(b).unwrap();
+    let parsed_diff = ParsedDiff::new(commit, Some(a), &test_repo.repo).unwrap();
+    parsed_diff.files[0]
+        .lines()
+        .map(|line| line.emphasis.iter().map(|range| line.content[range.clone()].to_string()).collect())
+        .collect()
+}
+
+#[test]
+fn changed_words_are_emphasized() {
+    let test_repo = TestRepo::new();
+    let lines = emphasis(&test_repo, b"let total = price * count;\n", b"let total = price * amount + 1;\n");
+    assert_eq!(lines, [vec!["count"], vec!["amount + 1"]]);
+}
+
+#[test]
+fn removed_and_added_lines_are_paired_in_order() {
+    let test_repo = TestRepo::new();
+    let lines = emphasis(
+        &test_repo,
+        b"keep\nfirst line here\nsecond line here\n",
+        b"keep\nfirst line there\nsecond line there\nthird line\n",
+    );
+    assert_eq!(lines, [
+        vec![],
+        vec!["here"],
+        vec!["here"],
+        vec!["there"],
+        vec!["there"],
+        vec![],
+    ]);
+}
+
+#[test]
+fn rewritten_lines_are_not_emphasized() {
+    let test_repo = TestRepo::new();
+    let lines = emphasis(&test_repo, b"fn parse(input: &str)\n", b"// nothing in common at all\n");
+    assert_eq!(lines, [Vec::<String>::new(), vec![]]);
+}
+
+#[test]
+fn lines_are_highlighted_after_the_language_of_their_file() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit_files("A", &[], &[("main.rs", b"fn main() {}\n", FileMode::Blob), ("notes", b"one\n", FileMode::Blob)], 100);
+    let b = test_repo.commit_files("B", &[a], &[("main.rs", b"fn main() { run(); }\n", FileMode::Blob), ("notes", b"two\n", FileMode::Blob)], 200);
+
+    let commit = test_repo.repo.find_commit(b).unwrap();
+    let mut parsed_diff = ParsedDiff::new(commit, Some(a), &test_repo.repo).unwrap();
+    parsed_diff.highlight_syntax(&SyntaxHighlighter::new());
+
+    let rust = parsed_diff.files.iter().find(|file| file.path() == "main.rs").unwrap();
+    for line in rust.lines() {
+        // The whole line is covered, and `fn` isn't the color of `main`.
+        assert_eq!(line.syntax.last().unwrap().0.end, line.content.len());
+        assert_ne!(line.syntax[0].1, line.syntax[2].1);
+    }
+    let unknown = parsed_diff.files.iter().find(|file| file.path() == "notes").unwrap();
+    assert!(unknown.lines().all(|line| line.syntax.is_empty()));
+}
+
+#[test]
+fn side_by_side_rows_face_removed_lines_with_added_ones() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit_files("A", &[], &[("f", b"keep\nold 1\nold 2\nold 3\nend\n", FileMode::Blob)], 100);
+    let b = test_repo.commit_files("B", &[a], &[("f", b"keep\nnew 1\nend\nmore\n", FileMode::Blob)], 200);
+
+    let commit = test_repo.repo.find_commit(b).unwrap();
+    let parsed_diff = ParsedDiff::new(commit, Some(a), &test_repo.repo).unwrap();
+    let rows = parsed_diff.files[0].hunks()[0]
+        .side_by_side()
+        .into_iter()
+        .map(|(old, new)| {
+            let side = |line: Option<&Line>| line.map(|line| line.content.trim_end().to_string()).unwrap_or_default();
+            (side(old), side(new))
+        })
+        .collect::<Vec<(String, String)>>();
+    let expected = [
+        ("keep", "keep"),
+        ("old 1", "new 1"),
+        ("old 2", ""),
+        ("old 3", ""),
+        ("end", "end"),
+        ("", "more"),
+    ];
+    assert_eq!(rows, expected.iter().map(|(old, new)| (old.to_string(), new.to_string())).collect::<Vec<_>>());
+}
+
+#[test]
+fn stat_counts_changed_lines_per_file() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit_files("A", &[], &[
+        ("src/big.rs", &numbered(&[]), FileMode::Blob),
+        ("image", b"\x00\x01", FileMode::Blob),
+        ("old_name", TEXT, FileMode::Blob),
+    ], 100);
+    let b = test_repo.commit_files("B", &[a], &[
+        ("src/big.rs", &numbered(&[(1, "one"), (2, "two"), (3, "three")]), FileMode::Blob),
+        ("image", b"\x00\x01\x02", FileMode::Blob),
+        ("new_name", TEXT, FileMode::Blob),
+        ("src/small.rs", b"fn small() {}\n", FileMode::Blob),
+    ], 200);
+
+    let commit = test_repo.repo.find_commit(b).unwrap();
+    let parsed_diff = ParsedDiff::new(commit, Some(a), &test_repo.repo).unwrap();
+    let stat = parsed_diff.stat(50)
+        .into_iter()
+        .map(|line| (line.name, line.changes, line.plus, line.minus))
+        .collect::<Vec<_>>();
+    assert_eq!(stat, [
+        (String::from("image"), String::from("Bin 2 -> 3 bytes"), 0, 0),
+        (String::from("old_name => new_name"), String::from("0"), 0, 0),
+        (String::from("src/big.rs"), String::from("6"), 3, 3),
+        (String::from("src/small.rs"), String::from("1"), 1, 0),
+    ]);
+    assert_eq!(parsed_diff.stat_summary(), "4 files changed, 4 insertions(+), 3 deletions(-)");
+
+    // Scaled down, the smallest change still shows.
+    let bars = parsed_diff.stat(2).into_iter().map(|line| (line.plus, line.minus)).collect::<Vec<_>>();
+    assert_eq!(bars, [(0, 0), (0, 0), (1, 1), (1, 0)]);
+}
+
+#[test]
+fn dirstat_shares_the_changes_between_directories() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit_files("A", &[], &[("README", b"read me\n", FileMode::Blob)], 100);
+    let b = test_repo.commit_files("B", &[a], &[
+        ("README", b"read me\n", FileMode::Blob),
+        ("src/lib.rs", b"one\ntwo\nthree\n", FileMode::Blob),
+        ("top", b"one\n", FileMode::Blob),
+    ], 200);
+
+    let commit = test_repo.repo.find_commit(b).unwrap();
+    let parsed_diff = ParsedDiff::new(commit, Some(a), &test_repo.repo).unwrap();
+    assert_eq!(parsed_diff.dirstat(), [(25.0, String::from("./")), (75.0, String::from("src/"))]);
+}
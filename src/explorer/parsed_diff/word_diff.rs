@@ -0,0 +1,103 @@
+use std::ops::Range;
+
+use super::{Hunk, LineOrigin};
+
+/// Lines with more words than this are left alone, comparing them costs words squared.
+const MAX_WORDS: usize = 400;
+
+/// Lines sharing less than this percentage of their text are rewritten rather than edited,
+/// emphasizing nearly all of them would say nothing.
+const MIN_COMMON_PERCENT: usize = 40;
+
+/// Byte ranges of a line.
+type Ranges = Vec<Range<usize>>;
+
+/// Pairs the removed and added lines of every change in `hunk`, in order, and marks in each pair
+/// the words that differ.
+pub fn highlight(hunk: &mut Hunk) {
+    let mut i = 0;
+    while i < hunk.lines.len() {
+        let deleted = hunk.lines[i..].iter().take_while(|line| line.origin == LineOrigin::Deletion).count();
+        let added = hunk.lines[i + deleted..].iter().take_while(|line| line.origin == LineOrigin::Addition).count();
+        for pair in 0..deleted.min(added) {
+            let (old, new) = (i + pair, i + deleted + pair);
+            if let Some((old_ranges, new_ranges)) = changed_ranges(&hunk.lines[old].content, &hunk.lines[new].content) {
+                hunk.lines[old].emphasis = old_ranges;
+                hunk.lines[new].emphasis = new_ranges;
+            }
+        }
+        i += (deleted + added).max(1);
+    }
+}
+
+/// Byte ranges of the words of `old` and `new` that aren't in their longest common subsequence,
+/// or None when the lines have too little in common.
+fn changed_ranges(old: &str, new: &str) -> Option<(Ranges, Ranges)> {
+    let (old, new) = (old.trim_end_matches('\n'), new.trim_end_matches('\n'));
+    let (old_words, new_words) = (words(old), words(new));
+    if old_words.len() > MAX_WORDS || new_words.len() > MAX_WORDS {
+        return None
+    }
+
+    // lengths[i][j]: longest common subsequence of old_words[i..] and new_words[j..].
+    let mut lengths = vec![vec![0usize; new_words.len() + 1]; old_words.len() + 1];
+    for i in (0..old_words.len()).rev() {
+        for j in (0..new_words.len()).rev() {
+            lengths[i][j] = if old[old_words[i].clone()] == new[new_words[j].clone()] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut old_kept, mut new_kept) = (vec![false; old_words.len()], vec![false; new_words.len()]);
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < old_words.len() && j < new_words.len() {
+        if old[old_words[i].clone()] == new[new_words[j].clone()] {
+            old_kept[i] = true;
+            new_kept[j] = true;
+            common += old_words[i].len();
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    if common * 100 < old.len().max(new.len()) * MIN_COMMON_PERCENT {
+        return None
+    }
+    Some((ranges(&old_words, &old_kept), ranges(&new_words, &new_kept)))
+}
+
+/// Runs of letters, digits and underscores, runs of whitespace, and every other character on its
+/// own, as byte ranges.
+fn words(line: &str) -> Vec<Range<usize>> {
+    let class = |c: char| if c.is_alphanumeric() || c == '_' { 1 } else if c.is_whitespace() { 2 } else { 0 };
+    let mut words: Vec<Range<usize>> = vec![];
+    let mut previous = None;
+    for (i, c) in line.char_indices() {
+        let end = i + c.len_utf8();
+        match words.last_mut() {
+            Some(word) if class(c) != 0 && previous == Some(class(c)) => word.end = end,
+            _ => words.push(i..end),
+        }
+        previous = Some(class(c));
+    }
+    words
+}
+
+// Adjacent changed words make a single range.
+fn ranges(words: &[Range<usize>], kept: &[bool]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = vec![];
+    for (word, _) in words.iter().zip(kept).filter(|(_, kept)| !**kept) {
+        match ranges.last_mut() {
+            Some(range) if range.end == word.start => range.end = word.end,
+            _ => ranges.push(word.clone()),
+        }
+    }
+    ranges
+}
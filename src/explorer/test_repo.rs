@@ -0,0 +1,79 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use git2::{Repository, Oid, Signature, Time, FileMode};
+use git2::build::TreeUpdateBuilder;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Throwaway repository for tests, removed from disk when dropped. Commits are built directly
+/// with git2 so their timestamps, and therefore the painted order, are deterministic.
+pub struct TestRepo {
+    pub path: PathBuf,
+    pub repo: Repository,
+}
+
+impl TestRepo {
+    pub fn new() -> Self {
+        let path = env::temp_dir().join(format!(
+            "git_explorer-test-{}-{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        let repo = Repository::init(&path).unwrap();
+        Self { path, repo }
+    }
+
+    /// Commits an empty tree on top of `parents` at `time` seconds after the epoch. No reference
+    /// is moved.
+    pub fn commit(&self, message: &str, parents: &[Oid], time: i64) -> Oid {
+        self.commit_at(message, parents, time, time)
+    }
+
+    /// Like `commit`, with different author and committer dates.
+    pub fn commit_at(&self, message: &str, parents: &[Oid], author_time: i64, committer_time: i64) -> Oid {
+        let tree_oid = self.repo.treebuilder(None).unwrap().write().unwrap();
+        self.commit_tree(message, parents, tree_oid, author_time, committer_time)
+    }
+
+    /// Commits `files`, given as path, content and mode. Paths may go through directories. A file
+    /// with `FileMode::Commit` is a submodule and its content the hex id it points to.
+    pub fn commit_files(&self, message: &str, parents: &[Oid], files: &[(&str, &[u8], FileMode)], time: i64) -> Oid {
+        let empty_tree = self.repo.find_tree(self.repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+        let mut updates = TreeUpdateBuilder::new();
+        for (path, content, mode) in files {
+            let oid = match mode {
+                FileMode::Commit => Oid::from_str(std::str::from_utf8(content).unwrap()).unwrap(),
+                _ => self.repo.blob(content).unwrap(),
+            };
+            updates.upsert(path, oid, *mode);
+        }
+        let tree_oid = updates.create_updated(&self.repo, &empty_tree).unwrap();
+        self.commit_tree(message, parents, tree_oid, time, time)
+    }
+
+    fn commit_tree(&self, message: &str, parents: &[Oid], tree_oid: Oid, author_time: i64, committer_time: i64) -> Oid {
+        let author = Signature::new("Tester", "tester@example.com", &Time::new(author_time, 0)).unwrap();
+        let committer = Signature::new("Tester", "tester@example.com", &Time::new(committer_time, 0)).unwrap();
+        let tree = self.repo.find_tree(tree_oid).unwrap();
+        let parents: Vec<_> = parents.iter().map(|oid| self.repo.find_commit(*oid).unwrap()).collect();
+        let parents: Vec<_> = parents.iter().collect();
+        self.repo.commit(None, &author, &committer, message, &tree, &parents).unwrap()
+    }
+
+    /// Creates (or moves) `refs/heads/<name>` to `oid`.
+    pub fn branch(&self, name: &str, oid: Oid) {
+        let commit = self.repo.find_commit(oid).unwrap();
+        self.repo.branch(name, &commit, true).unwrap();
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
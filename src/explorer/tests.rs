@@ -0,0 +1,137 @@
+use std::rc::Rc;
+
+use git2::Oid;
+
+use crate::explorer::GitExplorer;
+use crate::explorer::diff_base::DiffBase;
+use crate::explorer::error::ExplorerError;
+use crate::explorer::graph_order::GraphOrder;
+use crate::explorer::head_state::HeadState;
+use crate::explorer::test_repo::TestRepo;
+
+fn open(test_repo: &TestRepo) -> GitExplorer {
+    GitExplorer::new(Some(test_repo.path.to_str().unwrap().to_string()), GraphOrder::default()).unwrap()
+}
+
+fn summaries(git_explorer: &GitExplorer) -> Vec<String> {
+    git_explorer.nodes().iter().map(|node| node.summary.clone()).collect()
+}
+
+#[test]
+fn drives_the_explorer_without_a_terminal() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let b = test_repo.commit("B", &[a], 200);
+    let c = test_repo.commit("C", &[b], 300);
+    test_repo.branch("main", c);
+    test_repo.repo.set_head("refs/heads/main").unwrap();
+
+    let mut git_explorer = open(&test_repo);
+    git_explorer.run().unwrap();
+    assert_eq!(git_explorer.head_state(), HeadState::Branch { name: String::from("main"), oid: c });
+    assert_eq!(summaries(&git_explorer), ["C", "B", "A"]);
+    assert_eq!(git_explorer.diff_commit(c, DiffBase::default()).unwrap().compared, Some(b));
+    assert_eq!(git_explorer.children(b).unwrap(), [c]);
+
+    // The HEAD the UI shows and the graph it paints come from the same handle.
+    test_repo.repo.reference("refs/heads/main", b, true, "reset").unwrap();
+    git_explorer.run().unwrap();
+    assert_eq!(git_explorer.head_state().oid(), Some(b));
+    assert_eq!(summaries(&git_explorer), ["B", "A"]);
+}
+
+#[test]
+fn starts_at_a_revspec() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let b = test_repo.commit("B", &[a], 200);
+    let c = test_repo.commit("C", &[b], 300);
+    test_repo.branch("main", c);
+
+    let mut git_explorer = open(&test_repo);
+    assert_eq!(git_explorer.set_root("main~1").unwrap(), b);
+    assert!(git_explorer.set_root("no-such-revision").is_err());
+    git_explorer.run().unwrap();
+    assert_eq!(summaries(&git_explorer), ["B", "A"]);
+}
+
+#[test]
+fn reports_errors_instead_of_panicking() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    test_repo.branch("main", a);
+
+    let missing = test_repo.path.join("missing");
+    match GitExplorer::new(Some(missing.to_str().unwrap().to_string()), GraphOrder::default()) {
+        Err(ExplorerError::Open { .. }) => {},
+        Err(e) => panic!("unexpected error {}", e),
+        Ok(_) => panic!("opened a missing repository"),
+    }
+
+    let git_explorer = open(&test_repo);
+    let unknown = Oid::from_str("0123456789012345678901234567890123456789").unwrap();
+    assert!(matches!(git_explorer.diff_commit_by_id(a, Some(unknown)), Err(ExplorerError::CommitNotFound { oid, .. }) if oid == unknown));
+    assert!(matches!(git_explorer.diff_commit_by_id(unknown, None), Err(ExplorerError::CommitNotFound { .. })));
+}
+
+// Only Unix file names can be any bytes.
+#[cfg(unix)]
+#[test]
+fn opens_with_a_branch_name_that_isnt_utf8() {
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+    use git2::BranchType;
+
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    test_repo.branch("main", a);
+    let name = OsStr::from_bytes(b"caf\xe9");
+    fs::write(test_repo.path.join(".git/refs/heads").join(name), format!("{}\n", a)).unwrap();
+
+    let mut git_explorer = open(&test_repo);
+    git_explorer.run().unwrap();
+    let mut names: Vec<String> = git_explorer.branches(Some(BranchType::Local), None).iter().map(|branch| branch.shorthand().clone()).collect();
+    names.sort();
+    assert_eq!(names, ["caf\u{fffd}", "main"]);
+}
+
+#[test]
+fn diffs_are_rebuilt_only_when_what_they_show_changes() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let b = test_repo.commit("B", &[a], 200);
+    let c = test_repo.commit("C", &[b], 300);
+
+    let mut git_explorer = open(&test_repo);
+    let first = git_explorer.diff_commit(c, DiffBase::default()).unwrap();
+    assert!(Rc::ptr_eq(&first, &git_explorer.diff_commit(c, DiffBase::default()).unwrap()));
+
+    git_explorer.toggle_syntax_highlighting();
+    let plain = git_explorer.diff_commit(c, DiffBase::default()).unwrap();
+    assert!(!Rc::ptr_eq(&first, &plain));
+    assert_eq!(git_explorer.diff_commit(b, DiffBase::default()).unwrap().compared, Some(a));
+    assert_eq!(git_explorer.diff_commit(c, DiffBase::default()).unwrap().compared, Some(b));
+
+    // The branches tab compares HEAD to the stop branch on every redraw too.
+    let head_vs_a = git_explorer.diff_commit_by_id(c, Some(a)).unwrap();
+    assert!(Rc::ptr_eq(&head_vs_a, &git_explorer.diff_commit_by_id(c, Some(a)).unwrap()));
+    assert_eq!(git_explorer.diff_commit_by_id(c, Some(b)).unwrap().compared, Some(b));
+}
+
+#[test]
+fn ahead_behind_is_counted_for_the_branches_shown_only() {
+    let test_repo = TestRepo::new();
+    let a = test_repo.commit("A", &[], 100);
+    let b = test_repo.commit("B", &[a], 200);
+    let c = test_repo.commit("C", &[a], 300);
+    test_repo.branch("main", b);
+    test_repo.branch("topic", c);
+    test_repo.repo.set_head("refs/heads/main").unwrap();
+
+    let mut git_explorer = open(&test_repo);
+    git_explorer.run().unwrap();
+    let topic = git_explorer.branches(None, Some(&String::from("topic"))).remove(0);
+    assert_eq!(topic.ahead_behind_label(), "");
+    assert_eq!(git_explorer.with_ahead_behind(topic).ahead_behind_label(), "↑1 ↓1");
+}
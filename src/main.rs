@@ -1,9 +1,6 @@
 #![feature(iter_collect_into)]
-#![feature(slice_partition_dedup)]
 
-use explorer::branch_data::BranchData;
-use git2::{ Repository, BranchType };
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use explorer::GitExplorer;
 
 // use log::{trace, LevelFilter, SetLoggerError};
 use log::{trace, LevelFilter};
@@ -27,7 +24,11 @@ use tui::{
 
 use std::io;
 use std::env;
+use std::process;
 
+use cli::Cli;
+
+mod cli;
 mod ui;
 mod utils;
 mod explorer;
@@ -72,41 +73,44 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     trace!("Goes to file only");
     */
 
-    let args: Vec<String> = env::args().collect();
-    let stop_condition = args.get(1);
-
-    let repo = match Repository::open(".") {
-        Ok(repo) => repo,
-        Err(e) => panic!("failed to open: {}", e),
+    let cli = match Cli::parse(env::args().collect()) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(129);
+        }
+    };
+    let mut git_explorer = match GitExplorer::new(cli.repo.clone(), cli.order) {
+        Ok(git_explorer) => git_explorer,
+        Err(e) => {
+            eprintln!("fatal: {}", e);
+            process::exit(128);
+        }
     };
 
-    enable_raw_mode().expect("can run in raw mode");
+    if let Some(revspec) = &cli.revspec {
+        if let Err(e) = git_explorer.set_root(revspec) {
+            eprintln!("fatal: {}", e);
+            process::exit(128);
+        }
+    }
+    if let Some(stop_condition) = &cli.stop_condition {
+        if let Err(e) = git_explorer.set_stop_condition(stop_condition) {
+            eprintln!("fatal: {}", e);
+            process::exit(128);
+        }
+    }
 
     let stdout = io::stdout();
-    // execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    terminal.clear()?;
-
-    match stop_condition {
-        Some(stop_condition) => {
-            let mut branches = repo.branches(Some(BranchType::Local)).unwrap();
-            match branches.find(|b| b.as_ref().unwrap().0.get().shorthand().unwrap().to_string().contains(stop_condition)) {
-                Some(branch) => {
-                    let branch_data = BranchData::new(branch);
-                    ui::explorer_wrapper(&mut terminal, &repo, Some(branch_data))?
-                }
-                _ => ui::explorer_wrapper(&mut terminal, &repo, None)?,
-            };
-            
-        }
-        None => ui::explorer_wrapper(&mut terminal, &repo, None)?
+    // The terminal is back to normal by the time explorer_wrapper returns.
+    if let Err(e) = ui::explorer_wrapper(&mut terminal, &mut git_explorer) {
+        eprintln!("fatal: {}", e);
+        process::exit(1);
     }
 
-    disable_raw_mode()?;
-    terminal.show_cursor()?;
-
     // test_info(&repo);
 
     Ok(())
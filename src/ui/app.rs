@@ -1,7 +1,6 @@
 #![allow(unused)]  // FIXME
 
 use crossterm::event::{self, Event, KeyCode};
-use git2::Repository;
 
 use tui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
@@ -53,8 +52,11 @@ fn get_layout_chunks(size: Rect) -> Vec<Rect> {
         .split(size)
 }
 
-fn draw_status_bar<'layout>() -> Paragraph<'layout> {
-    let (title, color) = ("NORMAL MODE +++FILTER MODE CONTAIN+++", Color::LightCyan);
+fn draw_status_bar<'layout>(error: Option<&'layout str>) -> Paragraph<'layout> {
+    let (title, color) = match error {
+        Some(error) => (error, Color::Red),
+        None => ("NORMAL MODE +++FILTER MODE CONTAIN+++", Color::LightCyan),
+    };
 
     Paragraph::new(title)
         .style(Style::default().fg(color))
@@ -98,6 +100,8 @@ pub struct App<'a> {
     node_list_state: ListState,
     branches_component: BranchesComponent,
     graph_component: GraphComponent<'a>,
+    // Error of the last key press, shown in the status bar until the next one.
+    error: Option<String>,
 }
 
 impl App<'_> {
@@ -109,6 +113,7 @@ impl App<'_> {
         Self { 
             node_list_state,
             graph_component,
+            error: None,
             branches_component,
         }
     }
@@ -118,8 +123,7 @@ impl App<'_> {
     // pub fn app(
         &mut self,
         terminal: &mut Terminal<B>,
-        git_explorer: &mut GitExplorer,
-        repo: &Repository,) -> Result<(), Box<dyn std::error::Error>> {
+        git_explorer: &mut GitExplorer,) -> Result<(), Box<dyn std::error::Error>> {
 
         let mut tab_index = 0;
 
@@ -129,7 +133,7 @@ impl App<'_> {
             terminal.draw(|f| {
                 let mut chunks = get_layout_chunks(f.size());
 
-                let status_bar = draw_status_bar();
+                let status_bar = draw_status_bar(self.error.as_deref());
 
                 let tabs = draw_menu_tabs(&menu_titles, active_menu_item);
 
@@ -138,9 +142,9 @@ impl App<'_> {
                 match tab_index {
                     // 0 => wrapper(f, percentage_left, percentage_right, &mut self.node_list_state, &mut chunks, &git_explorer, repo),
                     // 0 => wrapper(f, percentage_left, percentage_right, &mut self.node_list_state, &mut chunks, &git_explorer, repo),
-                    0 => self.graph_component.render(f, &mut chunks, git_explorer, repo),
+                    0 => self.graph_component.render(f, &mut chunks, git_explorer),
                     // 1 => render_branches(f, &mut chunks),
-                    1 => self.branches_component.render(f, chunks[1], git_explorer, repo),
+                    1 => self.branches_component.render(f, chunks[1], git_explorer),
                     _ => {},
                 }
                 // wrapper(f, percentage_left, percentage_right, node_list_state, &mut chunks, &git_explorer, repo);
@@ -157,11 +161,12 @@ impl App<'_> {
                     KeyCode::Char('1') => { tab_index = 0 }
                     KeyCode::Char('2') => { tab_index = 1 }
                     key_code => {
-                        match tab_index {
-                            0 => {self.graph_component.event(key_code, git_explorer);},
-                            1 => {self.branches_component.event(key_code, git_explorer);},
-                            _ => {}
-                        }
+                        let result = match tab_index {
+                            0 => self.graph_component.event(key_code, git_explorer),
+                            1 => self.branches_component.event(key_code, git_explorer),
+                            _ => Ok(String::from("ok")),
+                        };
+                        self.error = result.err();
                     }
                 }
             }
@@ -1,6 +1,5 @@
 #![allow(unused)]  // FIXME
 
-use git2::Repository;
 use tui::{
     layout::{Alignment, Constraint, Direction, Rect, Layout},
     text::{Span, Spans},
@@ -13,6 +12,7 @@ use tui::{
 };
 
 use crate::ui::Component;
+use crate::ui::diff::{self, Summary};
 // use crossterm::event::Event;
 use crate::explorer::GitExplorer;
 use crossterm::event::KeyCode;
@@ -37,7 +37,6 @@ impl BranchesComponent {
         // rect: &mut Rect,
         rect: Rect,
         git_explorer: &GitExplorer,
-        repo: &Repository,
         ) {
         let vertical_chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -49,19 +48,20 @@ impl BranchesComponent {
         // let text = Spans::from(git_explorer.branches_strings());
         let mut text = vec![Spans::from(git_explorer.branches_strings())];
         
-        let head = repo.head().unwrap();
-        let current_commit = head.peel_to_commit().unwrap();
+        let head_state = git_explorer.head_state();
 
         text.push(
             Spans::from(vec![
-                Span::styled(format!("HEAD: {}", head.shorthand().unwrap()), Style::default().fg(Color::White))
-            ])
-        );
-        text.push(
-            Spans::from(vec![
-                Span::styled(format!("oid: {}", current_commit.id()), Style::default().fg(Color::White))
+                Span::styled(format!("HEAD: {}", head_state), Style::default().fg(Color::White))
             ])
         );
+        if let Some(oid) = head_state.oid() {
+            text.push(
+                Spans::from(vec![
+                    Span::styled(format!("oid: {}", oid), Style::default().fg(Color::White))
+                ])
+            );
+        }
 
         /*
         let text_2 = format!(
@@ -82,11 +82,17 @@ impl BranchesComponent {
 
         let compared_commit_oid = git_explorer.get_selected_branch_oid();
 
-        let parsed_diff = git_explorer.diff_commit_by_id(current_commit.clone(), compared_commit_oid);
-
+        // Nothing to compare on an unborn branch.
+        let lines = match head_state.oid() {
+            Some(head_oid) => match git_explorer.diff_commit_by_id(head_oid, compared_commit_oid) {
+                Ok(parsed_diff) => diff::unified(&parsed_diff, Summary::default(), vertical_chunks[1].width.saturating_sub(2)).lines,
+                Err(e) => vec![Spans::from(Span::styled(e.to_string(), Style::default().fg(Color::Red)))],
+            },
+            None => vec![Spans::from(format!("{}: no commits yet", head_state))],
+        };
 
         // let p2 = Paragraph::new(String::from(text_2))
-        let p2 = Paragraph::new(parsed_diff.test_lines)
+        let p2 = Paragraph::new(lines)
             .block(Block::default().title(format!("Commit COMPLETE")).borders(Borders::ALL))
             .style(Style::default().fg(Color::White).bg(Color::Black))
             .alignment(Alignment::Left)
@@ -109,10 +115,10 @@ impl Component for BranchesComponent {
             KeyCode::Tab => {
                 // TODO: Reset selected to zero to prevent bug when attempting to look at a
                 // commit that there is not anymore
-                git_explorer.update_graph(1);
+                git_explorer.update_graph(1).map_err(|e| e.to_string())?;
             }
             KeyCode::BackTab => {
-                git_explorer.update_graph(-1);
+                git_explorer.update_graph(-1).map_err(|e| e.to_string())?;
             }
             _ => {}
         }
@@ -0,0 +1,289 @@
+use git2::Delta;
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::ListItem,
+};
+use unicode_width::UnicodeWidthChar;
+
+use crate::utils::short_id;
+use crate::explorer::parsed_diff::{CommitDetails, FileDiff, Hunk, Line, LineOrigin, ParsedDiff};
+
+fn commit_spans(commit: &CommitDetails) -> Vec<Spans<'static>> {
+    let parents = commit.parents.iter().map(|oid| short_id(*oid)).collect::<Vec<String>>().join(" - ");
+    vec![
+        Spans::from(vec![Span::styled(commit.message.clone(), Style::default().fg(Color::White))]), // TODO: message will not generate spans with new lines
+        Spans::from(vec![Span::styled(format!("Committer: {}", commit.committer), Style::default().fg(Color::Red))]),
+        Spans::from(vec![Span::styled(format!("Author: {}", commit.author), Style::default().fg(Color::White))]),
+        Spans::from(vec![Span::styled(short_id(commit.id), Style::default().fg(Color::White))]),
+        Spans::from(vec![Span::styled(format!("PARENTS: {}", parents), Style::default().fg(Color::White))]),
+    ]
+}
+
+fn status_color(file: &FileDiff) -> Color {
+    match file.status {
+        Delta::Added => Color::Green,
+        Delta::Deleted => Color::Red,
+        Delta::Renamed | Delta::Copied => Color::Cyan,
+        _ => Color::White,
+    }
+}
+
+fn file_header(file: &FileDiff) -> Spans<'static> {
+    Spans::from(vec![Span::styled(file.to_string(), Style::default().fg(status_color(file)))])
+}
+
+fn hunk_header(hunk: &Hunk) -> Spans<'static> {
+    Spans::from(Span::styled(hunk.header.clone(), Style::default().fg(Color::DarkGray)))
+}
+
+/// Unified diffs, or old and new side by side when there's room for two columns.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DiffLayout {
+    #[default]
+    Auto,
+    Unified,
+    SideBySide,
+}
+
+impl DiffLayout {
+    /// Narrowest panel that fits two columns of 80 characters.
+    const SIDE_BY_SIDE_WIDTH: u16 = 160;
+
+    pub fn is_side_by_side(self, width: u16) -> bool {
+        match self {
+            DiffLayout::Auto => width >= Self::SIDE_BY_SIDE_WIDTH,
+            DiffLayout::Unified => false,
+            DiffLayout::SideBySide => true,
+        }
+    }
+
+    /// The other layout than the one shown at `width`.
+    pub fn toggle(self, width: u16) -> Self {
+        if self.is_side_by_side(width) { DiffLayout::Unified } else { DiffLayout::SideBySide }
+    }
+}
+
+// Syntax colors take the foreground, added and removed lines are told apart by a tint.
+fn line_style(line: &Line) -> (Style, Color, Color) {
+    let (color, tint, emphasis_tint) = match line.origin {
+        LineOrigin::Addition => (Color::Green, Color::Rgb(0, 40, 0), Color::Rgb(0, 90, 0)),
+        LineOrigin::Deletion => (Color::Red, Color::Rgb(50, 0, 0), Color::Rgb(110, 0, 0)),
+        LineOrigin::Context | LineOrigin::NoNewline => (Color::White, Color::Reset, Color::Reset),
+    };
+    (Style::default().fg(color), tint, emphasis_tint)
+}
+
+/// The content of `line` split where its syntax colors or emphasis change.
+fn content_spans(line: &Line) -> Vec<Span<'static>> {
+    let (plain, tint, emphasis_tint) = line_style(line);
+    let mut cuts = vec![0, line.content.len()];
+    for range in line.emphasis.iter().chain(line.syntax.iter().map(|(range, _)| range)) {
+        cuts.push(range.start);
+        cuts.push(range.end);
+    }
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    let mut spans = vec![];
+    for cut in cuts.windows(2) {
+        let (start, end) = (cut[0], cut[1]);
+        let mut style = plain;
+        if let Some((_, [r, g, b])) = line.syntax.iter().find(|(range, _)| range.contains(&start)) {
+            style = style.fg(Color::Rgb(*r, *g, *b)).bg(tint);
+        }
+        if line.emphasis.iter().any(|range| range.contains(&start)) {
+            style = style.bg(emphasis_tint).add_modifier(Modifier::BOLD);
+        }
+        spans.push(Span::styled(line.content[start..end].to_string(), style));
+    }
+    spans
+}
+
+fn line_spans(line: &Line) -> Spans<'static> {
+    let text = line.to_string();
+    // The content comes last, after the line number and origin.
+    let prefix = text[..text.len() - line.content.len()].to_string();
+    let mut spans = vec![Span::styled(prefix, line_style(line).0)];
+    spans.extend(content_spans(line));
+    Spans::from(spans)
+}
+
+/// One side of a side by side row: line number gutter and content, cut or padded to `width`.
+fn cell(line: Option<&Line>, lineno: Option<u32>, width: usize) -> Vec<Span<'static>> {
+    const GUTTER: usize = 5;
+    let gutter = match (line, lineno) {
+        (Some(line), Some(lineno)) => Span::styled(format!("{:>4} ", lineno), line_style(line).0),
+        _ => Span::raw(" ".repeat(GUTTER)),
+    };
+    let mut spans = vec![gutter];
+    let mut left = width.saturating_sub(GUTTER);
+    for span in line.map(content_spans).unwrap_or_default() {
+        // Tabs would push the column out of line, and so would counting a wide character as one.
+        let mut text = String::new();
+        for c in span.content.trim_end_matches('\n').replace('\t', "    ").chars() {
+            let c_width = c.width().unwrap_or(0);
+            if c_width > left {
+                break
+            }
+            left -= c_width;
+            text.push(c);
+        }
+        spans.push(Span::styled(text, span.style));
+    }
+    spans.push(Span::raw(" ".repeat(left)));
+    spans
+}
+
+/// What sums up the diff above the patch.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Summary {
+    /// Changed lines per file, like `git diff --stat`.
+    #[default]
+    Stat,
+    /// Share of the changes per directory, like `git diff --dirstat`.
+    Dirstat,
+}
+
+impl Summary {
+    pub fn toggle(self) -> Self {
+        match self {
+            Summary::Stat => Summary::Dirstat,
+            Summary::Dirstat => Summary::Stat,
+        }
+    }
+}
+
+/// The commit details, then the summary of the diff fitted to `width` and a blank line.
+fn head_spans(parsed_diff: &ParsedDiff, summary: Summary, width: u16) -> Vec<Spans<'static>> {
+    const MAX_BAR_WIDTH: usize = 50;
+    let mut spans = commit_spans(&parsed_diff.commit);
+    match summary {
+        Summary::Stat => {
+            let stat = parsed_diff.stat(MAX_BAR_WIDTH);
+            let name_width = stat.iter().map(|line| line.name.chars().count()).max().unwrap_or(0);
+            let changes_width = stat.iter().map(|line| line.changes.len()).max().unwrap_or(0);
+            // What's left after ` name | changes `, the bars are scaled again if it's too little.
+            let bar_width = (width as usize).saturating_sub(name_width + changes_width + 5).clamp(10, MAX_BAR_WIDTH);
+            let stat = parsed_diff.stat(bar_width);
+            for line in stat {
+                spans.push(Spans::from(vec![
+                    Span::raw(format!(" {:<name_width$} | {:>changes_width$} ", line.name, line.changes, name_width = name_width, changes_width = changes_width)),
+                    Span::styled("+".repeat(line.plus), Style::default().fg(Color::Green)),
+                    Span::styled("-".repeat(line.minus), Style::default().fg(Color::Red)),
+                ]));
+            }
+            spans.push(Spans::from(format!(" {}", parsed_diff.stat_summary())));
+        },
+        Summary::Dirstat => {
+            for (percent, directory) in parsed_diff.dirstat() {
+                spans.push(Spans::from(format!("{:>6.1}% {}", percent, directory)));
+            }
+        },
+    }
+    spans.push(Spans::from(vec![]));
+    spans
+}
+
+/// Where each file and each hunk starts among the rendered lines, to jump to them.
+#[derive(Default)]
+pub struct Anchors {
+    pub files: Vec<usize>,
+    pub hunks: Vec<usize>,
+}
+
+impl Anchors {
+    /// The file shown at line `offset`, None above the first one.
+    pub fn current_file(&self, offset: usize) -> Option<usize> {
+        self.files.iter().rposition(|row| *row <= offset)
+    }
+
+    /// Start of the file `step` files away from the one shown at `offset`, kept to the first and
+    /// last file. Above the first file, that's the first one.
+    pub fn step_file(&self, offset: usize, step: isize) -> Option<usize> {
+        let last = self.files.len().checked_sub(1)?;
+        let selected = match self.current_file(offset) {
+            Some(current) => (current as isize + step).clamp(0, last as isize) as usize,
+            None => 0,
+        };
+        Some(self.files[selected])
+    }
+
+    pub fn next(rows: &[usize], offset: usize) -> Option<usize> {
+        rows.iter().copied().find(|row| *row > offset)
+    }
+
+    pub fn previous(rows: &[usize], offset: usize) -> Option<usize> {
+        rows.iter().copied().rev().find(|row| *row < offset)
+    }
+}
+
+pub struct RenderedDiff {
+    pub lines: Vec<Spans<'static>>,
+    pub anchors: Anchors,
+}
+
+/// The commit details and the summary of the diff, then every file with its lines.
+pub fn unified(parsed_diff: &ParsedDiff, summary: Summary, width: u16) -> RenderedDiff {
+    let mut lines = head_spans(parsed_diff, summary, width);
+    let mut anchors = Anchors::default();
+    for file in parsed_diff.files.iter() {
+        anchors.files.push(lines.len());
+        lines.push(file_header(file));
+        for hunk in file.hunks() {
+            anchors.hunks.push(lines.len());
+            lines.push(hunk_header(hunk));
+            lines.extend(hunk.lines.iter().map(line_spans));
+        }
+    }
+    RenderedDiff { lines, anchors }
+}
+
+/// Like the unified diff, with the hunks of every file in two columns that fill `width`.
+/// Combined diffs have more than two sides, they stay unified.
+pub fn side_by_side(parsed_diff: &ParsedDiff, summary: Summary, width: u16) -> RenderedDiff {
+    let mut lines = head_spans(parsed_diff, summary, width);
+    let mut anchors = Anchors::default();
+
+    let column = (width as usize).saturating_sub(1) / 2;
+    let separator = Style::default().fg(Color::DarkGray);
+    for file in parsed_diff.files.iter() {
+        anchors.files.push(lines.len());
+        lines.push(file_header(file));
+        for hunk in file.hunks() {
+            anchors.hunks.push(lines.len());
+            lines.push(hunk_header(hunk));
+            if hunk.lines.iter().any(|line| !line.parents.is_empty()) {
+                lines.extend(hunk.lines.iter().map(line_spans));
+                continue
+            }
+            for (old, new) in hunk.side_by_side() {
+                let mut row = cell(old, old.and_then(|line| line.old_lineno), column);
+                row.push(Span::styled("│", separator));
+                row.extend(cell(new, new.and_then(|line| line.new_lineno), column));
+                lines.push(Spans::from(row));
+            }
+        }
+    }
+    RenderedDiff { lines, anchors }
+}
+
+/// A line per file: status letter, path and changed line counts.
+pub fn file_list(parsed_diff: &ParsedDiff) -> Vec<ListItem<'static>> {
+    parsed_diff.files.iter().map(|file| {
+        let path = match file.status {
+            Delta::Renamed | Delta::Copied => format!("{} -> {}", file.old_path, file.new_path),
+            _ => file.path().to_string(),
+        };
+        ListItem::new(Spans::from(vec![
+            Span::styled(format!("{} ", file.status_letter()), Style::default().fg(status_color(file))),
+            Span::raw(format!("{} ", path)),
+            Span::styled(format!("+{}", file.insertions), Style::default().fg(Color::Green)),
+            Span::raw(" "),
+            Span::styled(format!("-{}", file.deletions), Style::default().fg(Color::Red)),
+        ]))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests;
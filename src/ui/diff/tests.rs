@@ -0,0 +1,102 @@
+use git2::FileMode;
+use tui::text::Spans;
+use unicode_width::UnicodeWidthStr;
+
+use crate::explorer::parsed_diff::{Line, LineOrigin, ParsedDiff};
+use crate::explorer::test_repo::TestRepo;
+use crate::ui::diff::{cell, side_by_side, unified, Anchors, Summary};
+
+fn line(content: &str) -> Line {
+    Line {
+        origin: LineOrigin::Context,
+        old_lineno: Some(1),
+        new_lineno: Some(1),
+        content: content.to_string(),
+        parents: vec![],
+        emphasis: vec![],
+        syntax: vec![],
+    }
+}
+
+fn width(spans: &[tui::text::Span]) -> usize {
+    spans.iter().map(|span| span.content.width()).sum()
+}
+
+#[test]
+fn cells_are_cut_and_padded_to_their_display_width() {
+    let wide = line("日本語のテキスト\n");
+    let cut = cell(Some(&wide), Some(1), 12);
+    assert_eq!(width(&cut), 12);
+    // A wide character that would straddle the edge is left out, the gap is padded.
+    assert_eq!(cut[1].content, "日本語");
+
+    let tabs = line("\tx\n");
+    assert_eq!(width(&cell(Some(&tabs), Some(1), 20)), 20);
+    assert_eq!(width(&cell(None, None, 20)), 20);
+}
+
+/// A commit changing the first and last lines of `a`, two hunks, and the only line of `b`.
+fn two_files(test_repo: &TestRepo) -> ParsedDiff {
+    let text = (1..=20).map(|i| format!("{}\n", i)).collect::<String>();
+    let changed = text.replacen("1\n", "one\n", 1).replace("20\n", "twenty\n");
+    let old = test_repo.commit_files("old", &[], &[("a", text.as_bytes(), FileMode::Blob), ("b", b"x\n", FileMode::Blob)], 100);
+    let new = test_repo.commit_files("new", &[old], &[("a", changed.as_bytes(), FileMode::Blob), ("b", b"y\n", FileMode::Blob)], 200);
+    ParsedDiff::new(test_repo.repo.find_commit(new).unwrap(), Some(old), &test_repo.repo).unwrap()
+}
+
+fn text(spans: &Spans) -> String {
+    spans.0.iter().map(|span| span.content.as_ref()).collect()
+}
+
+#[test]
+fn anchors_point_at_file_and_hunk_headers_in_both_layouts() {
+    let test_repo = TestRepo::new();
+    let parsed_diff = two_files(&test_repo);
+
+    // Commit details, two stat lines, the stat summary and a blank line come first.
+    let rendered = unified(&parsed_diff, Summary::Stat, 80);
+    assert_eq!(rendered.anchors.files, [9, 22]);
+    assert_eq!(rendered.anchors.hunks, [10, 16, 23]);
+    let side = side_by_side(&parsed_diff, Summary::Stat, 80);
+    // Removed and added lines share a row.
+    assert_eq!(side.anchors.files, [9, 20]);
+    assert_eq!(side.anchors.hunks, [10, 15, 21]);
+
+    for rendered in [rendered, side] {
+        let headers = rendered.anchors.files.iter().map(|row| text(&rendered.lines[*row])).collect::<Vec<String>>();
+        assert_eq!(headers, ["a", "b"]);
+        for row in rendered.anchors.hunks.iter() {
+            assert!(text(&rendered.lines[*row]).starts_with("@@ -"));
+        }
+    }
+}
+
+#[test]
+fn jumps_stop_at_the_first_and_last_anchor() {
+    let anchors = Anchors { files: vec![9, 22], hunks: vec![10, 16, 23] };
+
+    // Above the first file.
+    assert_eq!(anchors.current_file(0), None);
+    assert_eq!(Anchors::next(&anchors.files, 0), Some(9));
+    assert_eq!(Anchors::previous(&anchors.files, 0), None);
+    assert_eq!(Anchors::previous(&anchors.hunks, 10), None);
+    assert_eq!(anchors.step_file(0, -1), Some(9));
+    assert_eq!(anchors.step_file(0, 1), Some(9));
+
+    // Inside the first file, on its header and in its second hunk.
+    assert_eq!(anchors.current_file(9), Some(0));
+    assert_eq!(anchors.current_file(18), Some(0));
+    assert_eq!(Anchors::next(&anchors.hunks, 10), Some(16));
+    assert_eq!(Anchors::previous(&anchors.hunks, 18), Some(16));
+    assert_eq!(anchors.step_file(18, 1), Some(22));
+    assert_eq!(anchors.step_file(18, -1), Some(9));
+
+    // After the last hunk.
+    assert_eq!(anchors.current_file(30), Some(1));
+    assert_eq!(Anchors::next(&anchors.hunks, 23), None);
+    assert_eq!(Anchors::next(&anchors.files, 30), None);
+    assert_eq!(Anchors::previous(&anchors.files, 30), Some(22));
+    assert_eq!(anchors.step_file(30, 1), Some(22));
+
+    assert_eq!(Anchors::default().step_file(0, 1), None);
+}
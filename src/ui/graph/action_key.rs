@@ -1,6 +1,7 @@
 use crossterm::event::KeyCode;
 
 use crate::explorer::GitExplorer;
+use crate::explorer::error::ExplorerError;
 
 enum ActionTypes {
     GitExplorer,
@@ -9,7 +10,7 @@ enum ActionTypes {
 
 pub struct ActionKey<'a> {
     key_code: KeyCode,
-    pub git_explorer_action: &'a dyn Fn(&mut GitExplorer)
+    pub git_explorer_action: &'a dyn Fn(&mut GitExplorer) -> Result<(), ExplorerError>
 }
 
 impl<'a> ActionKey<'a> {
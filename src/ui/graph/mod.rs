@@ -1,11 +1,10 @@
-use git2::{Repository, BranchType};
 use crossterm::event::KeyCode;
 use log::{trace, debug};
 
 use tui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::Spans,
+    text::{Span, Spans},
     terminal::Frame,
     widgets::{
         Block, BorderType, Borders, List, ListItem, ListState, Paragraph, Wrap, Clear,
@@ -13,8 +12,15 @@ use tui::{
     backend::Backend,
 };
 
+use git2::Oid;
+
 use crate::explorer::GitExplorer;
+use crate::explorer::graph_order::GraphOrder;
+use crate::explorer::diff_base::DiffBase;
+use crate::explorer::head_state::HeadState;
 use crate::ui::Component;
+use crate::ui::diff::{self, Anchors, DiffLayout, Summary};
+use crate::utils::short_id;
 
 use self::action_key::ActionKey;
 
@@ -22,6 +28,9 @@ use super::centered_rect_absolute;
 
 pub mod action_key;
 
+/// How many rows below the cursor must be painted before moving it.
+const LOOKAHEAD: usize = 20;
+
 pub struct GraphComponent<'a> {
     node_list_state: ListState,
     percentage_left: u16,
@@ -31,6 +40,31 @@ pub struct GraphComponent<'a> {
     action_key: ActionKey<'a>,
     edit_mode: bool,
     filter_string: String,
+    children_picker: Option<ChildrenPicker>,
+    // What the detail panel diffs the selected commit against.
+    diff_base: DiffBase,
+    diff_layout: DiffLayout,
+    summary: Summary,
+    // Files and hunks of the diff when last drawn, for the keys that jump between them.
+    diff_anchors: Anchors,
+    // Up and Down move through the file list instead of the graph.
+    file_list_focused: bool,
+    // Inside width of the detail panel when last drawn, it decides the automatic layout.
+    detail_width: u16,
+}
+
+/// Right side of the graph: the diff of the selected commit and the list of its files.
+pub struct DetailPane<'a> {
+    diff: Paragraph<'a>,
+    // With its selection, the current file, and the number of files.
+    file_list: Option<(List<'a>, ListState, usize)>,
+    anchors: Anchors,
+}
+
+/// Popup shown when the node under the cursor has more than one child.
+struct ChildrenPicker {
+    children: Vec<Oid>,
+    list_state: ListState,
 }
 
 impl Component for GraphComponent<'_> {
@@ -43,15 +77,15 @@ impl Component for GraphComponent<'_> {
             }
             KeyCode::Char('u') => {
                 // (self.action_key.git_explorer_action)(String::from("ups I did it again"));
-                (self.action_key.git_explorer_action)(git_explorer);
+                (self.action_key.git_explorer_action)(git_explorer).map_err(|e| e.to_string())?;
             }
             KeyCode::Tab => {
                 // TODO: Reset selected to zero to prevent bug when attempting to look at a
                 // commit that there is not anymore
-                git_explorer.update_graph(1);
+                git_explorer.update_graph(1).map_err(|e| e.to_string())?;
             }
             KeyCode::BackTab => {
-                git_explorer.update_graph(-1);
+                git_explorer.update_graph(-1).map_err(|e| e.to_string())?;
             }
             /*
             KeyCode::Char('q') => {
@@ -86,6 +120,60 @@ impl Component for GraphComponent<'_> {
                 // let current_commit = repo.find_commit(sub_tree_oid).unwrap();
                 // explorer_wrapper(terminal, repo, current_commit, None)?; // TODO: Add stop condition on recursion
             }
+            KeyCode::Char('c') => {
+                if let Some(oid) = self.node_list_state.selected().and_then(|i| git_explorer.get_node_id(i)) {
+                    let children = git_explorer.children(oid).map_err(|e| e.to_string())?;
+                    trace!("{} has {} children", oid, children.len());
+                    match children.len() {
+                        0 => {},
+                        1 => self.step_to_child(children[0], git_explorer)?,
+                        _ => {
+                            let mut list_state = ListState::default();
+                            list_state.select(Some(0));
+                            self.children_picker = Some(ChildrenPicker { children, list_state });
+                        }
+                    }
+                }
+            }
+            KeyCode::Char('o') => {
+                git_explorer.set_order(git_explorer.order().next()).map_err(|e| e.to_string())?;
+                self.node_list_state.select(Some(0));
+                self.diff_offset = 0;
+                self.diff_base = DiffBase::default();
+            }
+            KeyCode::Char('e') => {
+                // Expands (or collapses) the side branch of the merge under the cursor.
+                if git_explorer.order() == GraphOrder::FirstParent {
+                    if let Some(oid) = self.node_list_state.selected().and_then(|i| git_explorer.get_node_id(i)) {
+                        if git_explorer.parent_count(oid) > 1 {
+                            git_explorer.toggle_expanded(oid).map_err(|e| e.to_string())?;
+                        }
+                    }
+                }
+            }
+            KeyCode::Char('p') => {
+                // Cycles through the parents of the selected commit, then the combined diff of a merge.
+                if let Some(oid) = self.node_list_state.selected().and_then(|i| git_explorer.get_node_id(i)) {
+                    self.diff_base = self.diff_base.next(git_explorer.parent_count(oid));
+                    self.diff_offset = 0;
+                }
+            }
+            KeyCode::Char('s') => {
+                self.diff_layout = self.diff_layout.toggle(self.detail_width);
+            }
+            KeyCode::Char('f') => self.jump(Anchors::next(&self.diff_anchors.files, self.diff_offset)),
+            KeyCode::Char('F') => self.jump(Anchors::previous(&self.diff_anchors.files, self.diff_offset)),
+            KeyCode::Char('n') => self.jump(Anchors::next(&self.diff_anchors.hunks, self.diff_offset)),
+            KeyCode::Char('N') => self.jump(Anchors::previous(&self.diff_anchors.hunks, self.diff_offset)),
+            KeyCode::Char('l') => {
+                self.file_list_focused = !self.diff_anchors.files.is_empty();
+            }
+            KeyCode::Char('d') => {
+                self.summary = self.summary.toggle();
+            }
+            KeyCode::Char('h') => {
+                git_explorer.toggle_syntax_highlighting();
+            }
             KeyCode::Char('?') => {
                 self.help_toggled = !self.help_toggled;
                 trace!("HELP! {}", self.help_toggled);
@@ -93,9 +181,10 @@ impl Component for GraphComponent<'_> {
             }
             KeyCode::Down => {
                 if let Some(selected) = self.node_list_state.selected() {
+                    git_explorer.ensure_nodes(selected + 1 + LOOKAHEAD).map_err(|e| e.to_string())?;
                     let amount_nodes = git_explorer.get_nodes_len();
                     let node = git_explorer.get_node_id(selected);
-                    if selected >= amount_nodes - 1 {
+                    if selected + 1 >= amount_nodes {
                         trace!("DOWN");
                         // debug!("{:?} - parents: ", node, node.unwrap().parents());
                         trace!("DOWN");
@@ -107,6 +196,7 @@ impl Component for GraphComponent<'_> {
                         self.node_list_state.select(Some(selected + 1));
                     }
                     self.diff_offset = 0;
+                    self.diff_base = DiffBase::default();
                 }
             }
             KeyCode::Enter => {
@@ -121,13 +211,15 @@ impl Component for GraphComponent<'_> {
             }
             KeyCode::PageDown => {
                 if let Some(selected) = self.node_list_state.selected() {
+                    git_explorer.ensure_nodes(selected + 10 + LOOKAHEAD).map_err(|e| e.to_string())?;
                     let amount_nodes = git_explorer.get_nodes_len();
-                    if selected >= amount_nodes - 10 {
+                    if selected + 10 >= amount_nodes {
                         self.node_list_state.select(Some(0));
                     } else {
                         self.node_list_state.select(Some(selected + 10));
                     }
                     self.diff_offset = 0;
+                    self.diff_base = DiffBase::default();
                 }
             }
             KeyCode::Up => {
@@ -135,10 +227,12 @@ impl Component for GraphComponent<'_> {
                     let amount_nodes = git_explorer.get_nodes_len();
                     if selected > 0 {
                         self.node_list_state.select(Some(selected - 1));
-                    } else {
-                        self.node_list_state.select(Some(amount_nodes - 1));
+                    } else if git_explorer.is_exhausted() {
+                        // Only wrap around once the whole graph is painted, the bottom is unknown before.
+                        self.node_list_state.select(Some(amount_nodes.saturating_sub(1)));
                     }
                     self.diff_offset = 0;
+                    self.diff_base = DiffBase::default();
                 }
             }
             KeyCode::PageUp => {
@@ -146,10 +240,13 @@ impl Component for GraphComponent<'_> {
                     let amount_nodes = git_explorer.get_nodes_len();
                     if selected > 10 {
                         self.node_list_state.select(Some(selected - 10));
+                    } else if git_explorer.is_exhausted() {
+                        self.node_list_state.select(Some(amount_nodes.saturating_sub(1)));
                     } else {
-                        self.node_list_state.select(Some(amount_nodes - 1));
+                        self.node_list_state.select(Some(0));
                     }
                     self.diff_offset = 0;
+                    self.diff_base = DiffBase::default();
                 }
             }
             _ => {}
@@ -157,7 +254,27 @@ impl Component for GraphComponent<'_> {
         Ok(String::from("ok"))
     }
 	fn event(&mut self, key_code: KeyCode, git_explorer: &mut GitExplorer) -> Result<String, String> {
-        if self.edit_mode {
+        if let Some(picker) = self.children_picker.as_mut() {
+            let selected = picker.list_state.selected().unwrap_or(0);
+            match key_code {
+                KeyCode::Esc => { self.children_picker = None }
+                KeyCode::Down => picker.list_state.select(Some((selected + 1) % picker.children.len())),
+                KeyCode::Up => picker.list_state.select(Some((selected + picker.children.len() - 1) % picker.children.len())),
+                KeyCode::Enter => {
+                    let oid = picker.children[selected];
+                    self.children_picker = None;
+                    self.step_to_child(oid, git_explorer)?;
+                }
+                _ => {}
+            }
+        } else if self.file_list_focused {
+            match key_code {
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Char('l') => { self.file_list_focused = false }
+                KeyCode::Down => self.jump(self.diff_anchors.step_file(self.diff_offset, 1)),
+                KeyCode::Up => self.jump(self.diff_anchors.step_file(self.diff_offset, -1)),
+                _ => { self.command_mode_event(key_code, git_explorer)?; }
+            }
+        } else if self.edit_mode {
             match key_code {
                 KeyCode::Esc|KeyCode::F(2) => { self.edit_mode = false } // Gets traped in vim
                 KeyCode::Char(c) => {
@@ -190,16 +307,54 @@ impl GraphComponent<'_> {
             action_key,
             edit_mode: false,
             filter_string: String::new(),
+            children_picker: None,
+            diff_base: DiffBase::default(),
+            diff_layout: DiffLayout::default(),
+            summary: Summary::default(),
+            diff_anchors: Anchors::default(),
+            file_list_focused: false,
+            detail_width: 0,
+        }
+    }
+
+    // Scrolls the diff to `row`, staying put when there's nothing to jump to.
+    fn jump(&mut self, row: Option<usize>) {
+        if let Some(row) = row {
+            self.diff_offset = row;
+        }
+    }
+
+    fn step_to_child(&mut self, oid: Oid, git_explorer: &mut GitExplorer) -> Result<(), String> {
+        if let Some(i) = git_explorer.step_to_child(oid).map_err(|e| e.to_string())? {
+            self.node_list_state.select(Some(i));
+            self.diff_offset = 0;
+            self.diff_base = DiffBase::default();
+        }
+        Ok(())
+    }
+
+    fn render_children_picker<B: Backend>(&mut self, f: &mut Frame<B>, git_explorer: &GitExplorer) {
+        if let Some(picker) = self.children_picker.as_mut() {
+            let items: Vec<ListItem> = picker.children
+                .iter()
+                .map(|oid| ListItem::new(format!("{} {}", short_id(*oid), git_explorer.commit_summary(*oid))))
+                .collect();
+            let rect = centered_rect_absolute(65, items.len() as u16 + 2, f.size());
+            let list = List::new(items)
+                .block(Block::default().title("Children").borders(Borders::ALL).border_type(BorderType::Thick))
+                .highlight_style(Style::default().bg(Color::Yellow).fg(Color::Black));
+            f.render_widget(Clear, rect);
+            f.render_stateful_widget(list, rect, &mut picker.list_state);
         }
     }
 
-    pub fn render_home<'a>(&self, repo: &Repository, git_explorer: &'a GitExplorer) -> (List<'a>, Paragraph<'a>) {
-    // pub fn render_home<'a>(&self, repo: &Repository, git_explorer: &'a GitExplorer) -> (List<'a>, Text<'a>) {
+    pub fn render_home<'a>(&self, git_explorer: &'a GitExplorer, detail_width: u16) -> (List<'a>, DetailPane<'a>) {
+    // pub fn render_home<'a>(&self, git_explorer: &'a GitExplorer) -> (List<'a>, Text<'a>) {
         let style_list = Style::default().fg(Color::White);
         let nodes_block:Block = Block::default()
             .borders(Borders::ALL)
             .style(style_list)
-            .title(format!("Graph"))
+            .title(format!("Graph ({}) HEAD: {}", git_explorer.order(), git_explorer.head_state()))
             .border_type(BorderType::Plain);
 
         let items: Vec<ListItem> = git_explorer.nodes()
@@ -226,27 +381,76 @@ impl GraphComponent<'_> {
         // match repo.find_commit(sub_tree_oid) {
         match git_explorer.get_node_id(i) {
             Some(sub_tree_oid) => {
-                let current_commit = repo.find_commit(sub_tree_oid).unwrap();
+                let parent_count = git_explorer.parent_count(sub_tree_oid);
                 // let detail = git_explorer.diff_commit(current_commit, &data.get(i+1));
-                let detail = git_explorer.diff_commit(current_commit, i+1);
-
-                let spans_to_build = &detail.test_lines[self.diff_offset..].to_owned();
-
-                let node_detail = Paragraph::new(spans_to_build.clone())
-                    .block(Block::default().title(format!("Commit COMPLETE {} ", sub_tree_oid)).borders(Borders::ALL))
+                let detail = git_explorer.diff_commit(sub_tree_oid, self.diff_base);
+                let compared = match &detail {
+                    Ok(detail) => detail.compared.map(short_id).unwrap_or_default(),
+                    Err(_) => String::new(),
+                };
+                let mut title = match self.diff_base {
+                    _ if parent_count == 0 => format!("Commit COMPLETE {} (root commit) ", sub_tree_oid),
+                    DiffBase::Parent(parent) => format!("Commit COMPLETE {} vs parent {}/{} {} ", sub_tree_oid, parent + 1, parent_count, compared),
+                    DiffBase::Combined => format!("Commit COMPLETE {} combined diff of {} parents ", sub_tree_oid, parent_count),
+                };
+
+                let side_by_side = self.diff_layout.is_side_by_side(detail_width);
+                // A diff that can't be built is reported in its place.
+                let (lines, files, anchors) = match detail {
+                    Ok(detail) => {
+                        let rendered = if side_by_side {
+                            diff::side_by_side(&detail, self.summary, detail_width)
+                        } else {
+                            diff::unified(&detail, self.summary, detail_width)
+                        };
+                        let files = diff::file_list(&detail);
+                        (rendered.lines, files, rendered.anchors)
+                    },
+                    Err(e) => (vec![Spans::from(Span::styled(e.to_string(), Style::default().fg(Color::Red)))], vec![], Anchors::default()),
+                };
+
+                let current_file = anchors.current_file(self.diff_offset);
+                if let Some(file) = current_file {
+                    title.push_str(&format!("file {}/{} ", file + 1, files.len()));
+                }
+                let mut node_detail = Paragraph::new(lines.into_iter().skip(self.diff_offset).collect::<Vec<Spans>>())
+                    .block(Block::default().title(title).borders(Borders::ALL))
                     .style(Style::default().fg(Color::White).bg(Color::Black))
-                    .alignment(Alignment::Left)
-                    .wrap(Wrap { trim: true });
+                    .alignment(Alignment::Left);
+                // Wrapping would break the columns apart, the cells are cut to fit instead.
+                if !side_by_side {
+                    node_detail = node_detail.wrap(Wrap { trim: true });
+                }
 
-                (list, node_detail)
+                let file_list = if files.is_empty() {
+                    None
+                } else {
+                    let height = files.len();
+                    let block = match self.file_list_focused {
+                        true => Block::default().title("Files (Up/Down select, l back to the graph) ").border_type(BorderType::Thick),
+                        false => Block::default().title("Files (l select, f/F file, n/N hunk) "),
+                    };
+                    let list = List::new(files)
+                        .block(block.borders(Borders::ALL))
+                        .highlight_style(Style::default().bg(Color::Yellow).fg(Color::Black));
+                    let mut list_state = ListState::default();
+                    list_state.select(current_file);
+                    Some((list, list_state, height))
+                };
+
+                (list, DetailPane { diff: node_detail, file_list, anchors })
             },
             None => {
-                let node_detail = Paragraph::new("bla bla bla")
+                let message = match git_explorer.head_state() {
+                    HeadState::Unborn(name) => format!("unborn branch {}: no commits yet", name),
+                    _ => String::from("no commit selected"),
+                };
+                let node_detail = Paragraph::new(message)
                     .block(Block::default().title(format!("Commit COMPLETE ")).borders(Borders::ALL))
                     .style(Style::default().fg(Color::White).bg(Color::Black))
                     .alignment(Alignment::Left)
                     .wrap(Wrap { trim: true });
-                (list, node_detail)
+                (list, DetailPane { diff: node_detail, file_list: None, anchors: Anchors::default() })
             }
         }
     }
@@ -256,7 +460,6 @@ impl GraphComponent<'_> {
         f: &mut Frame<B>,
         chunks: &mut Vec<Rect>,
         git_explorer: &GitExplorer,
-        repo: &Repository,
         ) {
 
         if self.help_toggled {
@@ -323,14 +526,18 @@ impl GraphComponent<'_> {
 
             // let text = Spans::from(git_explorer.branches_strings()); // FIXME // TODO This has the branches matching current pattern
 
-            let branches: Vec<Spans> = git_explorer.branches(None, Some(&self.filter_string))
+            let branches = git_explorer.branches(None, Some(&self.filter_string));
+            let count = branches.len();
             // let branches: Vec<Spans> = git_explorer.branches(Some(BranchType::Local))
             // let branches: Vec<Spans> = git_explorer.branches(Some(BranchType::Remote))
-                .iter()
-                .map(|b| Spans::from(format!("{}", b)))
+            // Ahead/behind counts are only worth computing for the branches that fit below the count.
+            let shown: Vec<Spans> = branches
+                .into_iter()
+                .take(vertical_chunks[0].height.saturating_sub(1) as usize)
+                .map(|b| Spans::from(format!("{}", git_explorer.with_ahead_behind(b))))
                 .collect();
             // let text = [vec![Spans::from("count: ")], branches].concat();
-            let text = [vec![Spans::from(format!("count: {}", branches.len()))], branches].concat();
+            let text = [vec![Spans::from(format!("count: {}", count))], shown].concat();
             // let branches = Spans::from(git_explorer.branches(None));
 
             let paragraph = Paragraph::new(text);
@@ -342,9 +549,25 @@ impl GraphComponent<'_> {
                     [Constraint::Percentage(self.percentage_left), Constraint::Percentage(self.percentage_right)].as_ref(),
                 )
                 .split(vertical_chunks[1]);
-            let (left, right) = self.render_home(repo, git_explorer);
+            // Without the borders.
+            self.detail_width = nodes_chunks[1].width.saturating_sub(2);
+            let (left, right) = self.render_home(git_explorer, self.detail_width);
             f.render_stateful_widget(left, nodes_chunks[0], &mut self.node_list_state);
-            f.render_widget(right, nodes_chunks[1]);
+            match right.file_list {
+                Some((file_list, mut list_state, height)) => {
+                    // The file list takes up to a third of the panel, the diff the rest.
+                    let max_height = (nodes_chunks[1].height / 3).max(3);
+                    let detail_chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Length((height as u16 + 2).min(max_height)), Constraint::Min(3)].as_ref())
+                        .split(nodes_chunks[1]);
+                    f.render_stateful_widget(file_list, detail_chunks[0], &mut list_state);
+                    f.render_widget(right.diff, detail_chunks[1]);
+                },
+                None => f.render_widget(right.diff, nodes_chunks[1]),
+            }
+            self.diff_anchors = right.anchors;
+            self.render_children_picker(f, git_explorer);
         }
     }
 }
@@ -1,18 +1,20 @@
-use git2::{Repository, Oid};
-
 // use crossterm::event::Event;
 use crossterm::event::KeyCode;
 use log::info;
 // use crate::graph::GraphNode;
 // use crate::{utils::short_id, graph::GitExplorer};
 // use crate::explorer::{GitExplorer, GraphNode};
-use crate::explorer::{GitExplorer, branch_data::BranchData};
+use crate::explorer::GitExplorer;
 use crate::utils::short_id;
 use crate::explorer::graph_node::GraphNode;
+use crate::explorer::decoration::DecorationKind;
+use self::terminal_guard::TerminalGuard;
 
 mod graph;
 mod app;
 mod branches;
+mod terminal_guard;
+mod diff;
 
 use tui::{
     text::{Spans, Text, Span},
@@ -20,7 +22,7 @@ use tui::{
     backend::Backend,
     terminal::Frame,
     widgets::{ListState, ListItem},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     Terminal
 };
 
@@ -50,90 +52,102 @@ pub fn centered_rect_absolute(
 
 
 
+fn decoration_style(kind: DecorationKind) -> Style {
+    match kind {
+        DecorationKind::Head => Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        DecorationKind::LocalBranch => Style::default().fg(Color::Rgb(255, 50, 50)),
+        DecorationKind::RemoteBranch => Style::default().fg(Color::Yellow),
+        DecorationKind::LightweightTag => Style::default().fg(Color::Green),
+        DecorationKind::AnnotatedTag => Style::default().fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+        DecorationKind::Stash => Style::default().fg(Color::Gray),
+    }
+}
+
+fn decoration_spans(graph_node: &GraphNode) -> Vec<Span<'static>> {
+    if graph_node.decorations.is_empty() {
+        return vec![]
+    }
+    let mut spans = vec![Span::raw("[")];
+    for (i, decoration) in graph_node.decorations.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(", "));
+        }
+        spans.push(Span::styled(decoration.to_string(), decoration_style(decoration.kind)));
+    }
+    spans.push(Span::raw("] "));
+    spans
+}
+
 // impl From<&GraphNode> for Spans<'_> {
 impl From<&GraphNode> for ListItem<'_> {
     fn from(graph_node: &GraphNode) -> Self {
 
-        let (grapheme, oid, branch_shorthand, summary) = (graph_node.grapheme.clone(), graph_node.oid, &graph_node.branch_shorthand, &graph_node.summary);
-        let branch_shorthand = match branch_shorthand {
-            Some(b) => format!("[{}] ", b.to_string()),
-            None => String::new()
-        };
+        let (grapheme, oid, summary) = (graph_node.grapheme.clone(), graph_node.oid, &graph_node.summary);
 
         let oid = format!("{} ", short_id(oid));
         let graphemes = grapheme.split("\n").collect::<Vec<&str>>();
-
-        let spans = match graphemes.len() {
-            1 => {
-                vec![
-                    Spans::from(
-                        vec![
-                            Span::styled(graphemes[0].to_string(), Style::default().fg(Color::Rgb(50, 50, 255))),
-                            Span::raw(oid),
-                            Span::styled(branch_shorthand, Style::default().fg(Color::Rgb(255, 50, 50))),
-                            Span::raw(summary.clone(), ),
-                        ]
-                    )
-                ]
-            },
-            2 => {
-                vec![
-                    Spans::from(
-                        vec![
-                            Span::styled(graphemes[0].to_string(), Style::default().fg(Color::Rgb(50, 50, 255))),
-                            Span::raw(oid),
-                            Span::styled(branch_shorthand, Style::default().fg(Color::Rgb(255, 50, 50))),
-                            Span::raw(summary.clone(), ),
-                        ]
-                    ),
-                    Spans::from(
-                        vec![
-                            Span::styled(graphemes[1].to_string(), Style::default().fg(Color::Rgb(50, 50, 255))),
-                        ]
-                    )
-                ]
-            }
-            _ => {vec![]}
+        // Painting stops at the merge base with the stop branch, make it stand out.
+        let (oid_style, merge_base) = if graph_node.merge_base {
+            (Style::default().fg(Color::Black).bg(Color::Magenta), String::from("(merge base) "))
+        } else {
+            (Style::default(), String::new())
         };
 
+        let graph_style = Style::default().fg(Color::Rgb(50, 50, 255));
+        let mut first_line = vec![
+            Span::styled(graphemes[0].to_string(), graph_style),
+            Span::styled(oid, oid_style),
+        ];
+        first_line.extend(decoration_spans(graph_node));
+        first_line.push(Span::styled(merge_base, Style::default().fg(Color::Magenta)));
+        first_line.push(Span::styled(graph_node.merged_label(), Style::default().fg(Color::Cyan)));
+        first_line.push(Span::raw(summary.clone()));
+
+        let mut spans = vec![Spans::from(first_line)];
+        for grapheme in graphemes.iter().skip(1) {
+            spans.push(Spans::from(vec![Span::styled(grapheme.to_string(), graph_style)]));
+        }
+
         ListItem::new(spans)
     }
 }
- 
+
 impl From<&GraphNode> for Text<'_> {
     fn from(graph_node: &GraphNode) -> Self {
-        let (grapheme, oid, branch_shorthand, summary) = (&graph_node.grapheme, graph_node.oid, &graph_node.branch_shorthand, &graph_node.summary);
-        let branch_shorthand = match branch_shorthand {
-            Some(b) => format!("[{}] ", b.to_string()),
-            None => String::new()
+        let (grapheme, oid, summary) = (&graph_node.grapheme, graph_node.oid, &graph_node.summary);
+        let decorations = if graph_node.decorations.is_empty() {
+            String::new()
+        } else {
+            format!("[{}] ", graph_node.decorations.iter().map(|d| d.to_string()).collect::<Vec<String>>().join(", "))
         };
         Text::from(
             {
                 match grapheme.split_once("\n") {
-                    Some((g1, g_right)) => format!("{} ({}) {}{}\n{}", g1, short_id(oid), branch_shorthand, summary, g_right),
-                    None => format!("{} ({}) {}{}", grapheme, short_id(oid), branch_shorthand, summary),
+                    Some((g1, g_right)) => format!("{} ({}) {}{}{}\n{}", g1, short_id(oid), decorations, graph_node.merged_label(), summary, g_right),
+                    None => format!("{} ({}) {}{}{}", grapheme, short_id(oid), decorations, graph_node.merged_label(), summary),
                 }
             }
         )
     }
 }
- 
+
 
 
 
 // fn run_app<B: Backend>(terminal: &mut Terminal<B>) -> io::Result<()> {
 
 // pub fn explorer_wrapper<B: Backend>(terminal: &mut Terminal<B>, repo: &Repository, root_commit: Commit, stop_condition: Option<(Oid, String)>) -> Result<(), Box<dyn std::error::Error>> {
-pub fn explorer_wrapper<B: Backend>(terminal: &mut Terminal<B>, repo: &Repository, stop_condition: Option<BranchData>) -> Result<(), Box<dyn std::error::Error>> {
+pub fn explorer_wrapper<B: Backend>(terminal: &mut Terminal<B>, git_explorer: &mut GitExplorer) -> Result<(), Box<dyn std::error::Error>> {
+    // Gives the terminal back however this returns.
+    let _terminal_guard = TerminalGuard::new()?;
     let mut node_list_state = ListState::default();
-    let mut git_explorer = GitExplorer::new(None, None, stop_condition.clone()); // TARGET
-    git_explorer.run();
+    git_explorer.run()?;
     node_list_state.select(Some(0));
 
     // let (mut percentage_left, mut percentage_right) = (60, 40);
     terminal.clear()?;
     let mut app = app::App::new();
-    app.run(terminal, &mut git_explorer, repo)?;
+    app.run(terminal, git_explorer)?;
     // app::app(terminal, &mut node_list_state, &mut git_explorer, repo);
 
 
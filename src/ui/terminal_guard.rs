@@ -0,0 +1,44 @@
+use std::io;
+use std::panic;
+use std::sync::Once;
+
+use crossterm::{
+    cursor::Show,
+    execute, Result,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+static PANIC_HOOK: Once = Once::new();
+
+/// Puts the terminal in raw mode on the alternate screen for as long as it lives. The terminal
+/// is given back when the guard is dropped, on errors too, and before a panic is printed.
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn new() -> Result<Self> {
+        PANIC_HOOK.call_once(|| {
+            let default_hook = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                restore();
+                default_hook(info);
+            }));
+        });
+        enable_raw_mode()?;
+        // Dropped right away when entering the alternate screen fails, leaving raw mode.
+        let guard = Self;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+// Best effort: there is nowhere left to report a failure to.
+fn restore() {
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+    let _ = disable_raw_mode();
+}